@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     controller::{ControlCommand, ControlDriver},
     prelude::*,
@@ -7,7 +9,7 @@ use crate::{
 
 use super::{AiTask, AiTaskOutput};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AiDriver {
     tasks: VecDeque<AiTask>,
 }
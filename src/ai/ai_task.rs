@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{controller::ControlCommand, prelude::*};
 
 mod ai_handlers;
@@ -17,7 +19,7 @@ pub enum AiTaskOutput {
 }
 
 /// Represents the possible tasks of an ai actor.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum AiTask {
     /// Actor attacks the target.
     Attack { target: EntityRef },
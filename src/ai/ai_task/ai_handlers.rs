@@ -57,6 +57,14 @@ pub(super) fn attack_handler(
 
 pub(super) fn routine_handler(actor: &EntityRef, state: &impl StateReader) -> Vec<AiTaskOutput> {
     let mut priority_actions = get_urgent_actions(actor, state);
+    // Investigate the heard sounds. Not urgent, so that the following sounds do not interrupt the investigation.
+    if priority_actions.is_empty() {
+        if let Some(heard_pos) = try_get_heard_position(actor, state) {
+            priority_actions.push(AiTaskOutput::QueueFront(AiTask::MoveToPos(
+                AiMovementHandler::new(heard_pos),
+            )));
+        }
+    }
     priority_actions.insert(0, AiTaskOutput::QueueFront(AiTask::Routine));
     priority_actions
 }
@@ -1,6 +1,6 @@
 use crate::{
     character::{CharacterBundle, CharacterInsights},
-    physics::{Hitbox, ProjectileInsights},
+    physics::{HearingInsights, Hitbox, ProjectileInsights},
     prelude::*,
     vehicle::VehicleInsights,
 };
@@ -43,6 +43,19 @@ pub(super) fn try_move_towards_projectile(
     Some((target_pos.x, target_pos.y))
 }
 
+/// Returns the position of the loudest sound heard by the `actor` that was made by something it cannot see.
+pub(super) fn try_get_heard_position(
+    actor: &EntityRef,
+    state: &impl StateReader,
+) -> Option<(f32, f32)> {
+    let ai_char = state.read_bundle::<CharacterBundle>(actor)?;
+    StateInsights::of(state)
+        .heard_sounds_of(actor)
+        .into_iter()
+        .find(|sound| !ai_char.can_see(&sound.source, state))
+        .map(|sound| sound.pos)
+}
+
 /// Returns the actions that have the priority.
 pub(super) fn get_urgent_actions(actor: &EntityRef, state: &impl StateReader) -> Vec<AiTaskOutput> {
     // Move towards the projectile.
@@ -82,10 +95,7 @@ pub(super) fn reached_destination_approx(
     let cell_size = {
         state
             .select_one::<(Hitbox,)>(actor)
-            .map(|(hb,)| match hb.1 {
-                crate::physics::Shape::Circle { r } => 2. * r,
-                crate::physics::Shape::Rect { w, h } => w.max(h),
-            })
+            .map(|(hb,)| hb.1.extent())
             .unwrap_or(20.)
     };
     get_dpos(target_x, target_y, actor, state)
@@ -2,6 +2,7 @@ use itertools::Itertools;
 use std::collections::VecDeque;
 
 use notan::math;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     ai::MovementGrid,
@@ -14,7 +15,7 @@ use crate::{
 use super::{ai_helpers::*, AiTask, AiTaskOutput};
 
 /// A stateful handler for ai movement to a target position. Avoid obstacles & takes a shortest path.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AiMovementHandler {
     /// The current path that the ai is following.
     path_to_follow: VecDeque<(f32, f32)>,
@@ -90,10 +91,7 @@ fn compute_path(
     let cell_size = {
         state
             .select_one::<(Hitbox,)>(actor)
-            .map(|(hb,)| match hb.1 {
-                crate::physics::Shape::Circle { r } => 2. * r,
-                crate::physics::Shape::Rect { w, h } => w.max(h),
-            })? as isize
+            .map(|(hb,)| hb.1.extent())? as isize
     };
     let actor_char = state.read_bundle::<CharacterBundle>(actor)?;
     // Read the hitboxes that we must try to avoid.
@@ -60,9 +60,9 @@ impl MovementGrid {
                 h: self.cell_size as f32,
             };
             let cell_hb = TransformedShape::new(&transform, &shape);
-            let is_colliding = hitboxes.iter().any(|target_hb| {
-                sepax2d::sat_overlap(target_hb.shape.shape_ref(), cell_hb.shape_ref())
-            });
+            let is_colliding = hitboxes
+                .iter()
+                .any(|target_hb| target_hb.shape.overlaps(&cell_hb));
             *free = !is_colliding;
         });
     }
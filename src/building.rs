@@ -1,7 +1,9 @@
 use std::collections::HashSet;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    physics::{Hitbox, HitboxType, Shape, VisionField},
+    physics::{CollisionFilter, Hitbox, HitboxType, Layers, Shape, VisionField},
     prelude::*,
     sprite::Sprite,
 };
@@ -26,7 +28,7 @@ impl WallDirection {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Building;
 
 #[derive(Clone, Copy, Debug)]
@@ -59,19 +61,21 @@ impl BuildingBundle {
                     w: width,
                     h: height,
                 },
+                CollisionFilter::new(Layers::SENSORS, Layers::CHARACTERS),
             ),
             InteractTarget::<Hitbox>::default(),
             Sprite::new(format!("{}/ceilings", sprite_id), 20).with_tiling(num_tiles, num_tiles),
         ));
         let floors = cmds.create_from((
             Transform::default(),
-            AnchorTransform(building, (0., 0.), 0.),
+            AnchorTransform((0., 0.), 0.),
             Hitbox(
                 HitboxType::Ghost,
                 Shape::Rect {
                     w: width,
                     h: height,
                 },
+                CollisionFilter::new(Layers::NONE, Layers::NONE),
             ),
             Sprite::new(format!("{}/floors", sprite_id), 0).with_tiling(num_tiles, num_tiles),
         ));
@@ -84,7 +88,7 @@ impl BuildingBundle {
                 cmds,
             )
             .primary_entity();
-            cmds.set_component(&wall, AnchorTransform(building, (-width / 2., 0.), 90.));
+            cmds.set_component(&wall, AnchorTransform((-width / 2., 0.), 90.));
             wall
         } else {
             cmds.create_entity()
@@ -98,7 +102,7 @@ impl BuildingBundle {
                 cmds,
             )
             .primary_entity();
-            cmds.set_component(&wall, AnchorTransform(building, (0., -height / 2.), 0.));
+            cmds.set_component(&wall, AnchorTransform((0., -height / 2.), 0.));
             wall
         } else {
             cmds.create_entity()
@@ -112,7 +116,7 @@ impl BuildingBundle {
                 cmds,
             )
             .primary_entity();
-            cmds.set_component(&wall, AnchorTransform(building, (width / 2., 0.), 90.));
+            cmds.set_component(&wall, AnchorTransform((width / 2., 0.), 90.));
             wall
         } else {
             cmds.create_entity()
@@ -126,7 +130,7 @@ impl BuildingBundle {
                 cmds,
             )
             .primary_entity();
-            cmds.set_component(&wall, AnchorTransform(building, (0., height / 2.), 0.));
+            cmds.set_component(&wall, AnchorTransform((0., height / 2.), 0.));
             wall
         } else {
             cmds.create_entity()
@@ -204,13 +208,14 @@ impl WallBundle {
         let wall = cmds.create_from((trans,));
         let left = cmds.create_from((
             Transform::default(),
-            AnchorTransform(wall, (-size / 4. - door_offset / 2., 0.), 0.),
+            AnchorTransform((-size / 4. - door_offset / 2., 0.), 0.),
             Hitbox(
                 HitboxType::Static,
                 Shape::Rect {
                     w: size / 2. - door_offset,
                     h: wall_size,
                 },
+                CollisionFilter::new(Layers::WALLS, Layers::ALL),
             ),
             InteractTarget::<Hitbox>::default(),
             InteractTarget::<VisionField>::default(),
@@ -218,19 +223,20 @@ impl WallBundle {
         ));
         let right = cmds.create_from((
             Transform::default(),
-            AnchorTransform(wall, (size / 4. + door_offset / 2., 0.), 0.),
+            AnchorTransform((size / 4. + door_offset / 2., 0.), 0.),
             Hitbox(
                 HitboxType::Static,
                 Shape::Rect {
                     w: size / 2. - door_offset,
                     h: wall_size,
                 },
+                CollisionFilter::new(Layers::WALLS, Layers::ALL),
             ),
             InteractTarget::<Hitbox>::default(),
             InteractTarget::<VisionField>::default(),
             Sprite::new(format!("{}/walls", sprite_id), 19).with_tiling(num_tiles, 1),
         ));
-        Self { wall, left, right }
+        cmds.push_bundle(Self { wall, left, right })
     }
 }
 
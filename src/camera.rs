@@ -1,7 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
 /// The entity tagged by this component will be followed by the camera.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct CameraFollow {
     pub w: f32,
     pub h: f32,
@@ -13,6 +15,33 @@ impl CameraFollow {
     }
 }
 
+/// A resource pointing to the entity followed by the camera.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ActiveCamera(pub EntityRef);
+
+impl ActiveCamera {
+    /// Returns the transform & the camera settings of the entity followed by the camera.
+    pub fn read(state: &impl StateReader) -> Option<(&Transform, &CameraFollow)> {
+        let camera = state.read_resource::<ActiveCamera>()?;
+        state.select_one::<(Transform, CameraFollow)>(&camera.0)
+    }
+
+    /// Returns the top-left & bottom-right corners of the area seen by the camera.
+    pub fn bounds(state: &impl StateReader) -> Option<((f32, f32), (f32, f32))> {
+        Self::read(state).map(|(trans, camera)| {
+            (
+                (trans.x - camera.w / 2., trans.y - camera.h / 2.),
+                (trans.x + camera.w / 2., trans.y + camera.h / 2.),
+            )
+        })
+    }
+
+    /// Returns the distance from the center to the corners of the area seen by the camera.
+    pub fn view_distance(state: &impl StateReader) -> Option<f32> {
+        Self::read(state).map(|(_, camera)| (camera.w * camera.w + camera.h * camera.h).sqrt() / 2.)
+    }
+}
+
 /// Converts from world coordinates to the screen coordinates.
 pub fn map_to_screen_cords(
     world_x: f32,
@@ -21,7 +50,7 @@ pub fn map_to_screen_cords(
     screen_height: f32,
     state: &impl StateReader,
 ) -> (f32, f32) {
-    if let Some((_, (_, trans))) = state.select::<(CameraFollow, Transform)>().next() {
+    if let Some((trans, _)) = ActiveCamera::read(state) {
         (
             world_x - trans.x + screen_width / 2.,
             world_y - trans.y + screen_height / 2.,
@@ -39,7 +68,7 @@ pub fn map_to_world_cords(
     screen_height: f32,
     state: &impl StateReader,
 ) -> (f32, f32) {
-    if let Some((_, (_, trans))) = state.select::<(CameraFollow, Transform)>().next() {
+    if let Some((trans, _)) = ActiveCamera::read(state) {
         (
             screen_x + trans.x - screen_width / 2.,
             screen_y + trans.y - screen_height / 2.,
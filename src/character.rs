@@ -6,6 +6,14 @@ pub use character_bundle::*;
 pub use character_insights::*;
 pub use character_tags::*;
 
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::EntityRef;
+
 /// Represents an `alive` character in the game.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Character;
+
+/// A resource pointing to the character controlled by the user.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Player(pub EntityRef);
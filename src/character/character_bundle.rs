@@ -22,7 +22,11 @@ impl CharacterBundle {
             TargetVelocity::default(),
             TargetRotation::default(),
             MaxSpeed(300.),
-            Hitbox(HitboxType::Dynamic, Shape::Rect { w: 20., h: 20. }),
+            Hitbox(
+                HitboxType::Dynamic,
+                Shape::Rect { w: 20., h: 20. },
+                CollisionFilter::new(Layers::CHARACTERS, Layers::ALL),
+            ),
             InteractTarget::<Hitbox>::default(),
             Equipment::new([
                 EquipmentSlot::Head,
@@ -30,6 +34,7 @@ impl CharacterBundle {
                 EquipmentSlot::Backpack,
                 EquipmentSlot::LeftHand,
                 EquipmentSlot::RightHand,
+                EquipmentSlot::WeaponAmmo,
                 EquipmentSlot::Legs,
                 EquipmentSlot::Feet,
             ]),
@@ -42,18 +47,36 @@ impl CharacterBundle {
             ]),
             InteractTarget::<VisionField>::default(),
         ));
-        let vf_radius = 200.;
+        cmds.set_components(
+            &character,
+            (
+                RigidBody::new(70., 0., 0.2),
+                // Footsteps.
+                MovementNoise::new(250., 0.4),
+                Hearing(600.),
+            ),
+        );
+        let vf = VisionField::new(120., 100., 400., 1.5);
+        // The hitbox covers the whole cone, which is around the character.
         let vision_field = cmds.create_from((
             Transform::default(),
-            AnchorTransform(character, (vf_radius, 0.), 0.),
-            Hitbox(HitboxType::Ghost, Shape::Circle { r: vf_radius }),
+            AnchorTransform((0., 0.), 0.),
+            Hitbox(
+                HitboxType::Ghost,
+                Shape::Circle { r: vf.far },
+                CollisionFilter::new(Layers::SENSORS, Layers::ALL.without(Layers::SENSORS)),
+            ),
             InteractTarget::<Hitbox>::default(),
-            VisionField(vf_radius),
+            vf,
         ));
         let collision_senser = cmds.create_from((
             Transform::default(),
-            AnchorTransform(character, (0., 0.), 0.),
-            Hitbox(HitboxType::Ghost, Shape::Rect { w: 30., h: 30. }),
+            AnchorTransform((0., 0.), 0.),
+            Hitbox(
+                HitboxType::Ghost,
+                Shape::Rect { w: 30., h: 30. },
+                CollisionFilter::new(Layers::SENSORS, Layers::ALL.without(Layers::SENSORS)),
+            ),
             InteractTarget::<Hitbox>::default(),
         ));
         cmds.push_bundle(Self {
@@ -39,12 +39,14 @@ impl TagSource for Character {
             .select_one::<(TargetVelocity,)>(e)
             .map(|(target_vel,)| target_vel.x == 0. && target_vel.y == 0.)
             .unwrap_or(true);
-        let is_driving = state
-            .select::<(InteractTarget<Vehicle>,)>()
-            .any(|(_, (vehicle_intr,))| vehicle_intr.actors.contains(e));
-        let is_shooting = state
-            .select::<(InteractTarget<ProjectileGenerator>,)>()
-            .any(|(_, (p_gen_intr,))| p_gen_intr.actors.contains(e));
+        let is_driving = StateInsights::of(state)
+            .interactions_of::<Vehicle>(e)
+            .next()
+            .is_some();
+        let is_shooting = StateInsights::of(state)
+            .interactions_of::<ProjectileGenerator>(e)
+            .next()
+            .is_some();
         if is_driving {
             tags.insert(CharacterTag::Driving);
         } else if is_idle {
@@ -1,5 +1,7 @@
 use std::marker::PhantomData;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{item::EquipmentSlot, prelude::*};
 
 mod equipment_interaction;
@@ -14,7 +16,7 @@ pub use proximity_interaction::{ProximityInteractable, ProximityInteractionSyste
 pub use user_input_driver::*;
 
 /// Entities with this component will be able to be moved by the given [`ControlDriver`].
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Controller<D: ControlDriver>(pub D);
 
 /// A command that can be emitted by a [`ControlDriver`].
@@ -26,9 +28,10 @@ pub enum ControlCommand {
     ProximityUninteract,
     EquipmentInteract(EquipmentSlot),
     EquipmentUninteract(EquipmentSlot),
+    EquipmentReload(EquipmentSlot),
 }
 
-pub trait ControlDriver: 'static + Clone + std::fmt::Debug {
+pub trait ControlDriver: 'static + Clone + std::fmt::Debug + Send + Sync {
     /// Returns [`ControlCommand`]s from the given state of the game.
     fn get_commands(
         &mut self,
@@ -100,6 +103,9 @@ impl<D: ControlDriver, R: StateReader> System<R> for ControlSystem<D> {
                     ControlCommand::EquipmentUninteract(slot) => {
                         cmds.emit_event(EquipmentUninteractReq(actor, slot))
                     }
+                    ControlCommand::EquipmentReload(slot) => {
+                        cmds.emit_event(EquipmentReloadReq(actor, slot))
+                    }
                 });
             });
     }
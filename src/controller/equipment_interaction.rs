@@ -1,5 +1,5 @@
 use crate::{
-    item::{Equipment, EquipmentSlot},
+    item::{Equipment, EquipmentSlot, Magazine},
     prelude::*,
 };
 
@@ -11,6 +11,9 @@ pub struct EquipmentInteractReq(pub EntityRef, pub EquipmentSlot);
 #[derive(Clone, Copy, Debug)]
 pub struct EquipmentUninteractReq(pub EntityRef, pub EquipmentSlot);
 
+#[derive(Clone, Copy, Debug)]
+pub struct EquipmentReloadReq(pub EntityRef, pub EquipmentSlot);
+
 /// A system that handles the entities that can interact with their equipment.
 #[derive(Clone, Copy, Debug)]
 pub struct EquipmentInteractionSystem;
@@ -23,7 +26,8 @@ impl<R: StateReader> System<R> for EquipmentInteractionSystem {
                     .get_item_stack(&evt.1)
                     .and_then(|item_slot| item_slot.head_item());
                 if let Some(item) = item_at_slot {
-                    cmds.emit_event(TryInteractReq::new(evt.0, *item));
+                    let req = TryInteractReq::new(evt.0, *item, cmds.rng());
+                    cmds.emit_event(req);
                 }
             }
         });
@@ -35,9 +39,20 @@ impl<R: StateReader> System<R> for EquipmentInteractionSystem {
                         .get_item_stack(&evt.1)
                         .and_then(|item_slot| item_slot.head_item());
                     if let Some(item) = item_at_slot {
-                        cmds.emit_event(TryUninteractReq::new(evt.0, *item));
+                        let req = TryUninteractReq::new(evt.0, *item, cmds.rng());
+                        cmds.emit_event(req);
                     }
                 }
             });
+        state.read_events::<EquipmentReloadReq>().for_each(|evt| {
+            if let Some((equipment,)) = state.select_one::<(Equipment,)>(&evt.0) {
+                let item_at_slot = equipment
+                    .get_item_stack(&evt.1)
+                    .and_then(|item_slot| item_slot.head_item());
+                if let Some(item) = item_at_slot {
+                    cmds.emit_event(InteractReq::<Magazine>::new(evt.0, *item));
+                }
+            }
+        });
     }
 }
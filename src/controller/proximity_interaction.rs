@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{physics::ColliderInsights, prelude::*};
 
 use super::{TryInteractReq, TryUninteractReq};
 
 /// An actor that can be interacted by colliding entities by user input.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ProximityInteractable;
 
 /// A system that handles the entities that can interact with their surroundings.
@@ -31,7 +33,8 @@ impl<R: StateReader> System<R> for ProximityInteractionSystem {
                         .iter()
                         .filter(|e| state.select_one::<(ProximityInteractable,)>(e).is_some())
                         .for_each(|target| {
-                            cmds.emit_event(TryInteractReq::new(actor, *target));
+                            let req = TryInteractReq::new(actor, *target, cmds.rng());
+                            cmds.emit_event(req);
                         });
                 }
             });
@@ -45,7 +48,8 @@ impl<R: StateReader> System<R> for ProximityInteractionSystem {
                         .iter()
                         .filter(|e| state.select_one::<(ProximityInteractable,)>(e).is_some())
                         .for_each(|target| {
-                            cmds.emit_event(TryUninteractReq::new(actor, *target));
+                            let req = TryUninteractReq::new(actor, *target, cmds.rng());
+                            cmds.emit_event(req);
                         });
                 }
             });
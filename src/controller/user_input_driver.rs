@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 use crate::vehicle::VehicleInsights;
 
 use super::*;
 
 /// Uses key presses to control the entities.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct UserInputDriver;
 
 impl ControlDriver for UserInputDriver {
@@ -50,6 +52,12 @@ fn get_character_commands(
                 EquipmentSlot::RightHand,
             )];
         }
+        if ctx.control_map.reload_was_pressed {
+            return vec![
+                ControlCommand::EquipmentReload(EquipmentSlot::LeftHand),
+                ControlCommand::EquipmentReload(EquipmentSlot::RightHand),
+            ];
+        }
         let speed = game_state
             .select_one::<(MaxSpeed,)>(&actor)
             .map(|(max_speed,)| max_speed.0)
@@ -3,9 +3,11 @@ use std::{
     marker::PhantomData,
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{item::ItemInsights, physics::ColliderInsights, prelude::*};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Effect {
     Multiply(f32),
     Add(f32),
@@ -41,7 +43,7 @@ impl AffectibleComponent for Acceleration {
 }
 
 /// A component representing another affected component.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Affected<T: AffectibleComponent> {
     /// The initial state of the component.
     initial_state: Option<T>,
@@ -67,7 +69,7 @@ impl<T: AffectibleComponent> Affected<T> {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EffectorTarget {
     /// The effect will be applied to the storer of the `Effector`.
     Storer,
@@ -78,7 +80,7 @@ pub enum EffectorTarget {
 }
 
 /// A component representing an entity that can apply effects to other entities.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Effector<T: AffectibleComponent> {
     effect: Effect,
     targets: HashSet<EffectorTarget>,
@@ -118,11 +120,14 @@ impl<T> Default for EffectSystem<T> {
 }
 
 impl<T: AffectibleComponent, R: StateReader> System<R> for EffectSystem<T> {
-    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
-        // Apply the effects.
+    fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+        // Apply the effects, only when they have changed.
         state
-            .select::<(Affected<T>, T)>()
-            .for_each(|(e, (affected, target_component))| {
+            .changed_since::<Affected<T>>(ctx.last_run)
+            .for_each(|(e, affected)| {
+                let Some((target_component,)) = state.select_one::<(T,)>(&e) else {
+                    return;
+                };
                 // Either copy in the saved initial state, or read from the current state.
                 let initial_state = affected
                     .initial_state
@@ -130,10 +135,12 @@ impl<T: AffectibleComponent, R: StateReader> System<R> for EffectSystem<T> {
                     .unwrap_or(target_component.clone());
                 // Calculate the final state using the applied effects.
                 let final_state = affected.final_state(initial_state.clone());
-                // Update the saved initial state and the final state of the component.
-                cmds.update_component(&e, move |affected: &mut Affected<T>| {
-                    affected.initial_state = Some(initial_state);
-                });
+                // Save the initial state once, as the target component is overwritten from now on.
+                if affected.initial_state.is_none() {
+                    cmds.update_component(&e, move |affected: &mut Affected<T>| {
+                        affected.initial_state = Some(initial_state);
+                    });
+                }
                 cmds.set_component(&e, final_state);
             });
         // Emit effect application requests.
@@ -0,0 +1,369 @@
+use std::{fmt::Display, path::PathBuf, time::Instant};
+
+use crate::{
+    building::Building,
+    camera::ActiveCamera,
+    character::Character,
+    item::Item,
+    physics::{HitEvt, Hitter},
+    prelude::*,
+    replay::{FrameInput, Recorder, Replay},
+    vehicle::Vehicle,
+    world_gen::WorldGenerator,
+};
+
+/// The fixed time step used by the simulation.
+pub const FRAME_DT: f32 = 1. / 60.;
+
+/// Scripted inputs that stand in for the user during a headless simulation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputScript {
+    /// No keys are pressed.
+    Idle,
+    /// The player walks around in a square, shooting & interacting periodically.
+    Wander,
+}
+
+impl InputScript {
+    /// Returns the control map for the given frame.
+    pub fn control_map(&self, frame: usize, state: &impl StateReader) -> ControlMap {
+        match self {
+            InputScript::Idle => ControlMap::default(),
+            InputScript::Wander => {
+                // Change the walking direction every 2 seconds.
+                let dir = (frame / 120) % 4;
+                // Aim towards the walking direction.
+                let (x, y) = ActiveCamera::read(state)
+                    .map(|(trans, _)| (trans.x, trans.y))
+                    .unwrap_or_default();
+                let aim = [(100., 0.), (0., 100.), (-100., 0.), (0., -100.)][dir];
+                // Hold the trigger for half a second, every second.
+                let trigger_time = frame % 60;
+                ControlMap {
+                    right_is_down: dir == 0,
+                    down_is_down: dir == 1,
+                    left_is_down: dir == 2,
+                    up_is_down: dir == 3,
+                    start_interact_was_pressed: frame % 180 == 90,
+                    mouse_left_was_pressed: trigger_time == 0,
+                    mouse_left_is_down: trigger_time < 30,
+                    mouse_left_was_released: trigger_time == 30,
+                    mouse_pos: (x + aim.0, y + aim.1),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for InputScript {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "idle" => Ok(InputScript::Idle),
+            "wander" => Ok(InputScript::Wander),
+            _ => Err(anyhow::anyhow!("unknown input script {:?}", s)),
+        }
+    }
+}
+
+/// Statistics about the state of a simulated world.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorldStats {
+    pub frame: usize,
+    pub entities: usize,
+    pub characters: usize,
+    pub vehicles: usize,
+    pub items: usize,
+    pub projectiles: usize,
+    pub buildings: usize,
+    /// The number of hits since the previous statistics.
+    pub hits: usize,
+}
+
+impl WorldStats {
+    pub fn of(frame: usize, state: &impl StateReader) -> Self {
+        Self {
+            frame,
+            entities: state.cloned_entity_manager().num_alive(),
+            characters: state.select::<(Character,)>().count(),
+            vehicles: state.select::<(Vehicle,)>().count(),
+            items: state.select::<(Item,)>().count(),
+            projectiles: state.select::<(Hitter,)>().count(),
+            buildings: state.select::<(Building,)>().count(),
+            hits: 0,
+        }
+    }
+}
+
+impl Display for WorldStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "frame {}: {} entities, {} characters, {} vehicles, {} items, {} projectiles, {} buildings, {} hits",
+            self.frame,
+            self.entities,
+            self.characters,
+            self.vehicles,
+            self.items,
+            self.projectiles,
+            self.buildings,
+            self.hits
+        )
+    }
+}
+
+/// Steps a world with the registered systems without a window or a renderer.
+pub struct HeadlessRunner {
+    world: SystemManager<State>,
+    frame: usize,
+    /// Provides the inputs when there is no replay, or the replay is over.
+    script: InputScript,
+    replay: Option<Replay>,
+    recorder: Option<Recorder>,
+    /// Counts the hits in between the statistics.
+    hit_reader: EventReader<HitEvt>,
+}
+
+impl HeadlessRunner {
+    pub fn new(world: SystemManager<State>, script: InputScript) -> Self {
+        Self {
+            world,
+            frame: 0,
+            script,
+            replay: None,
+            recorder: None,
+            hit_reader: Default::default(),
+        }
+    }
+
+    /// Feeds the inputs from the given replay before falling back to the script.
+    pub fn with_replay(self, replay: Replay) -> Self {
+        Self {
+            replay: Some(replay),
+            ..self
+        }
+    }
+
+    /// Records the inputs of the simulated frames with the given recorder.
+    pub fn with_recorder(self, recorder: Recorder) -> Self {
+        Self {
+            recorder: Some(recorder),
+            ..self
+        }
+    }
+
+    /// Returns the simulated world.
+    pub fn world(&self) -> &SystemManager<State> {
+        &self.world
+    }
+
+    /// Returns the number of simulated frames.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Simulates a single frame.
+    pub fn step(&mut self) -> anyhow::Result<()> {
+        let input = self
+            .replay
+            .as_mut()
+            .and_then(Replay::next_input)
+            .unwrap_or_else(|| FrameInput {
+                ctx: UpdateContext {
+                    dt: FRAME_DT,
+                    control_map: self.script.control_map(self.frame, self.world.get_state()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&input)?;
+        }
+        input.apply(&mut self.world);
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// Simulates the given number of frames.
+    pub fn run(&mut self, num_frames: usize) -> anyhow::Result<()> {
+        for _ in 0..num_frames {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the statistics of the current state of the world.
+    /// The hits are counted since the previous call, as long as the events of [`HitEvt`] are kept for long enough.
+    pub fn stats(&mut self) -> WorldStats {
+        let state = self.world.get_state();
+        WorldStats {
+            hits: self.hit_reader.read(state).count(),
+            ..WorldStats::of(self.frame, state)
+        }
+    }
+}
+
+/// The options of a headless run.
+#[derive(Clone, Debug)]
+struct HeadlessOptions {
+    /// Total number of frames to simulate. Defaults to the length of the replay if there is one.
+    frames: Option<usize>,
+    /// Print the statistics every this many frames.
+    report_every: usize,
+    script: InputScript,
+    /// The seed of the generated world.
+    seed: u64,
+    /// Load the world from this snapshot instead of generating one.
+    load: Option<PathBuf>,
+    /// Save the final state of the world into this snapshot.
+    dump: Option<PathBuf>,
+    /// Record the session into this file.
+    record: Option<PathBuf>,
+    /// Replay the session recorded in this file.
+    replay: Option<PathBuf>,
+    /// Run the systems one after another on the main thread.
+    serial: bool,
+    /// Print the order of the systems before running them.
+    print_schedule: bool,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            frames: None,
+            report_every: 60,
+            script: InputScript::Idle,
+            seed: 0,
+            load: None,
+            dump: None,
+            record: None,
+            replay: None,
+            serial: false,
+            print_schedule: false,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let mut opts = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or(anyhow::anyhow!("missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--frames" => opts.frames = Some(value()?.parse()?),
+                "--report-every" => opts.report_every = value()?.parse()?,
+                "--script" => opts.script = value()?.parse()?,
+                "--seed" => opts.seed = value()?.parse()?,
+                "--load" => opts.load = Some(value()?.into()),
+                "--dump" => opts.dump = Some(value()?.into()),
+                "--record" => opts.record = Some(value()?.into()),
+                "--replay" => opts.replay = Some(value()?.into()),
+                "--serial" => opts.serial = true,
+                "--print-schedule" => opts.print_schedule = true,
+                _ => return Err(anyhow::anyhow!("unknown argument {:?}", arg)),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// Runs a headless simulation configured by the given command line arguments.
+///
+/// `--frames N --report-every N --script idle|wander --seed N --load PATH --dump PATH --record PATH --replay PATH --serial --print-schedule`
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let opts = HeadlessOptions::parse(args)?;
+    let (mut world, replay) = match (&opts.replay, &opts.load) {
+        (Some(path), _) => {
+            let (world, replay) = Replay::load(path)?;
+            (world, Some(replay))
+        }
+        (None, Some(path)) => (WorldGenerator::load(path)?, None),
+        (None, None) => (WorldGenerator::generate_debug_world(opts.seed), None),
+    };
+    world.set_serial(opts.serial);
+    // Keep the hits around until they are counted in the next report.
+    let report_every = opts.report_every.max(1);
+    world.update_with(|_, cmds| cmds.set_event_retention::<HitEvt>(report_every));
+    if opts.print_schedule {
+        for entry in world.scheduled_systems()? {
+            if entry.runs_in_parallel() {
+                println!("{} (parallel)", entry.name());
+            } else {
+                println!("{}", entry.name());
+            }
+        }
+    }
+    let frames = opts
+        .frames
+        .or(replay.as_ref().map(Replay::remaining))
+        .unwrap_or(600);
+    let mut runner = HeadlessRunner::new(world, opts.script);
+    if let Some(replay) = replay {
+        runner = runner.with_replay(replay);
+    }
+    if let Some(path) = &opts.record {
+        let recorder = Recorder::create(path, runner.world())?;
+        runner = runner.with_recorder(recorder);
+    }
+    println!("{}", runner.stats());
+    let start = Instant::now();
+    while runner.frame() < frames {
+        let num_frames = report_every.min(frames - runner.frame());
+        runner.run(num_frames)?;
+        println!("{}", runner.stats());
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "simulated {} frames in {:.2?} ({:.3} ms/frame)",
+        frames,
+        elapsed,
+        elapsed.as_secs_f64() * 1000. / frames.max(1) as f64
+    );
+    if let Some(path) = &opts.dump {
+        WorldGenerator::save(runner.world(), path)?;
+        println!("saved the world into {}", path.display());
+    }
+    Ok(())
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::character::Player;
+
+    #[test]
+    fn test_headless_run() {
+        let world = WorldGenerator::generate_debug_world(3);
+        let mut runner = HeadlessRunner::new(world, InputScript::Wander);
+        let player_pos = |runner: &HeadlessRunner| {
+            let state = runner.world().get_state();
+            let player = state.read_resource::<Player>().unwrap().0;
+            let (trans,) = state.select_one::<(Transform,)>(&player).unwrap();
+            (trans.x, trans.y)
+        };
+        let start = player_pos(&runner);
+        let before = runner.stats();
+        assert!(before.characters > 0 && before.items > 0);
+        runner.run(60).unwrap();
+        let after = runner.stats();
+        assert_eq!((runner.frame(), after.frame), (60, 60));
+        assert!(after.entities > 0 && after.characters > 0);
+        // The player walks to the right in the first seconds of the script.
+        let end = player_pos(&runner);
+        assert!(end.0 > start.0, "{:?} -> {:?}", start, end);
+        // The options of the command line are parsed.
+        let args = ["--frames", "10", "--script", "wander", "--serial"].map(String::from);
+        let opts = HeadlessOptions::parse(&args).unwrap();
+        assert_eq!(opts.frames, Some(10));
+        assert_eq!(opts.script, InputScript::Wander);
+        assert!(opts.serial);
+        assert!(HeadlessOptions::parse(&["--frames".to_string()]).is_err());
+    }
+}
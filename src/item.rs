@@ -1,9 +1,13 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     character::{CharacterBundle, CharacterInsights},
     controller::ProximityInteractable,
     prelude::*,
 };
 
+pub use ammo::*;
+pub use ammo_insights::*;
 pub use create_item::*;
 pub use equipment::*;
 pub use item_description::*;
@@ -12,6 +16,8 @@ pub use item_stack::*;
 pub use item_tags::*;
 pub use storage::*;
 
+mod ammo;
+mod ammo_insights;
 mod create_item;
 mod equipment;
 mod item_description;
@@ -21,7 +27,7 @@ mod item_tags;
 mod storage;
 
 /// Represents an entity that can be equipped, stored, and dropped on the ground.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Item(f32);
 
 impl Item {
@@ -37,7 +43,7 @@ impl Item {
 }
 
 /// Represents the location of an item.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ItemLocation {
     Ground,
     Equipment(EntityRef),
@@ -45,7 +51,7 @@ pub enum ItemLocation {
 }
 
 /// A request to transfer an item entity between locations. Handled by [`ItemTransferSystem`].
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ItemTransferReq {
     /// The item entity to transfer.
     pub item_entity: EntityRef,
@@ -209,6 +215,7 @@ impl<R: StateReader> System<R> for ItemPickupSystem {
             .filter(|item| StateInsights::of(state).location_of(item) == ItemLocation::Ground)
             .for_each(|item| {
                 cmds.remove_component::<AnchorTransform>(&item);
+                cmds.remove_parent(&item);
                 cmds.set_component(&item, ProximityInteractable);
             });
         // Handle transfer to equipment/storage.
@@ -222,10 +229,8 @@ impl<R: StateReader> System<R> for ItemPickupSystem {
             )
             .for_each(|(actor, item)| {
                 cmds.remove_component::<ProximityInteractable>(&item);
-                cmds.set_components(
-                    &item,
-                    (Transform::default(), AnchorTransform(actor, (0., 0.), 0.)),
-                );
+                cmds.set_components(&item, (Transform::default(), AnchorTransform((0., 0.), 0.)));
+                cmds.set_parent(&item, &actor);
             });
         state
             .read_events::<InteractionStartedEvt<Item>>()
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{physics::ProjectileGenerator, prelude::*};
+
+use super::{
+    AmmoInsights, EquipmentInsights, ItemLocation, ItemTransferReq, ItemUnequippedEvt, Storage,
+};
+
+/// The calibre of a round, which determines the guns that can fire it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Calibre {
+    Pistol,
+    Rifle,
+}
+
+/// An item that can be fired by the guns of the same calibre. Each round is used up when fired.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Ammo(pub Calibre);
+
+/// Guns with a magazine fire the rounds loaded in their [`Storage`], which are loaded from the ammo slot of the equipper by reloading.
+/// Guns without a magazine fire the rounds in the ammo slot directly.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Magazine {
+    /// The maximum number of rounds that can be loaded.
+    pub capacity: usize,
+    /// The time in seconds it takes to reload.
+    pub reload_time: f32,
+}
+
+impl Magazine {
+    pub fn new(capacity: usize, reload_time: f32) -> Self {
+        Self {
+            capacity,
+            reload_time,
+        }
+    }
+}
+
+/// Reloading a [`Magazine`] is an interaction between the equipper and the gun, which lasts for the reload time.
+impl Interaction for Magazine {
+    fn priority() -> usize {
+        Storage::priority() + 20
+    }
+
+    /// Returns true if the magazine is not full and the equipper has rounds to load.
+    fn can_start_targeted(actor: &EntityRef, target: &EntityRef, state: &impl StateReader) -> bool {
+        let Some((magazine,)) = state.select_one::<(Magazine,)>(target) else {
+            return false;
+        };
+        let insights = StateInsights::of(state);
+        insights.is_equipping(actor, target)
+            && insights.loaded_rounds_of(target).len() < magazine.capacity
+            && !insights.spare_rounds_for(actor, target).is_empty()
+    }
+
+    /// Returns false. Can only be started explicitly by targeted requests.
+    fn can_start_untargeted(
+        _actor: &EntityRef,
+        _target: &EntityRef,
+        _state: &impl StateReader,
+    ) -> bool {
+        false
+    }
+
+    /// Can only be ended explicitly by targeted requests, so that releasing the trigger does not cancel the reload.
+    fn can_end_untargeted(
+        _actor: &EntityRef,
+        _target: &EntityRef,
+        _state: &impl StateReader,
+    ) -> bool {
+        false
+    }
+}
+
+/// A request to load the rounds into the magazine, emitted once the reload time is over.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CompleteReloadReq {
+    actor_entity: EntityRef,
+    gun_entity: EntityRef,
+}
+
+/// A system that handles the reloading of the [`Magazine`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct ReloadSystem;
+
+impl<R: StateReader> System<R> for ReloadSystem {
+    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+        // Cancel the reload upon unequipping the gun.
+        state.read_events::<ItemUnequippedEvt>().for_each(|evt| {
+            if Magazine::interaction_exists(&evt.equipment_entity, &evt.item_entity, state) {
+                cmds.emit_event(UninteractReq::<Magazine>::new(
+                    evt.equipment_entity,
+                    evt.item_entity,
+                ));
+            }
+        });
+        // Stop firing while reloading, and complete the reload after the reload time.
+        state
+            .read_events::<InteractionStartedEvt<Magazine>>()
+            .for_each(|evt| {
+                let Some((magazine,)) = state.select_one::<(Magazine,)>(&evt.target) else {
+                    return;
+                };
+                if ProjectileGenerator::interaction_exists(&evt.actor, &evt.target, state) {
+                    cmds.emit_event(UninteractReq::<ProjectileGenerator>::new(
+                        evt.actor, evt.target,
+                    ));
+                }
+                cmds.set_component(
+                    &evt.target,
+                    TimedEmit::new(
+                        magazine.reload_time,
+                        CompleteReloadReq {
+                            actor_entity: evt.actor,
+                            gun_entity: evt.target,
+                        },
+                    ),
+                );
+            });
+        // Move the rounds from the ammo slot into the magazine.
+        state.read_events::<CompleteReloadReq>().for_each(|evt| {
+            let (actor, gun) = (evt.actor_entity, evt.gun_entity);
+            // The reload might have been cancelled in the meantime.
+            if !Magazine::interaction_exists(&actor, &gun, state) {
+                return;
+            }
+            let insights = StateInsights::of(state);
+            let missing = state
+                .select_one::<(Magazine,)>(&gun)
+                .map(|(magazine,)| magazine.capacity)
+                .unwrap_or_default()
+                .saturating_sub(insights.loaded_rounds_of(&gun).len());
+            insights
+                .spare_rounds_for(&actor, &gun)
+                .into_iter()
+                .take(missing)
+                .for_each(|round| {
+                    cmds.emit_event(ItemTransferReq {
+                        item_entity: round,
+                        from_loc: ItemLocation::Equipment(actor),
+                        to_loc: ItemLocation::Storage(gun),
+                    });
+                });
+            cmds.emit_event(UninteractReq::<Magazine>::new(actor, gun));
+        });
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::{
+        character::Character,
+        item::{Equipment, EquipmentSlot, ItemInsights},
+        world_gen::*,
+    };
+
+    #[test]
+    fn test_fire_and_reload() {
+        let mut world = WorldGenerator::generate(WorldTemplate::new([(
+            Transform::at(0., 0.),
+            BANDIT_TEMPLATE,
+        )]));
+        let run_for = |world: &mut SystemManager<State>, secs: f32| {
+            for _ in 0..(secs * 60.) as usize {
+                world.update_with_systems(UpdateContext {
+                    dt: 1. / 60.,
+                    ..Default::default()
+                });
+            }
+        };
+        run_for(&mut world, 0.1);
+        let (gun, _) = world.get_state().select::<(Magazine,)>().next().unwrap();
+        let bandit = StateInsights::of(world.get_state())
+            .equipper_of(&gun)
+            .unwrap();
+        let rounds = |world: &SystemManager<State>| {
+            let insights = StateInsights::of(world.get_state());
+            (
+                insights.loaded_rounds_of(&gun).len(),
+                insights.spare_rounds_for(&bandit, &gun).len(),
+            )
+        };
+        // The bandit comes with a loaded gun and a spare magazine.
+        assert_eq!(rounds(&world), (30, 25));
+        // Firing uses up the loaded rounds.
+        world.update_with(|_, cmds| {
+            cmds.emit_event(InteractReq::<ProjectileGenerator>::new(bandit, gun));
+        });
+        run_for(&mut world, 0.5);
+        world.update_with(|_, cmds| {
+            cmds.emit_event(UninteractReq::<ProjectileGenerator>::new(bandit, gun));
+        });
+        run_for(&mut world, 0.1);
+        let (loaded, spare) = rounds(&world);
+        assert!(loaded < 30 && spare == 25);
+        // Reloading takes a while, and fills the magazine from the ammo slot.
+        world.update_with(|_, cmds| {
+            cmds.emit_event(InteractReq::<Magazine>::new(bandit, gun));
+        });
+        run_for(&mut world, 1.);
+        assert!(StateInsights::of(world.get_state()).is_reloading(&gun));
+        assert_eq!(rounds(&world), (loaded, spare));
+        run_for(&mut world, 1.5);
+        assert!(!StateInsights::of(world.get_state()).is_reloading(&gun));
+        assert_eq!(rounds(&world), (30, 25 - (30 - loaded)));
+    }
+
+    #[test]
+    fn test_ammo_loot() {
+        let mut world = WorldGenerator::generate(WorldTemplate::new([
+            (Transform::at(0., 0.), PLAYER_TEMPLATE),
+            (Transform::at(200., 0.), CHEST_TEMPLATE),
+        ]));
+        for _ in 0..6 {
+            world.update_with_systems(UpdateContext {
+                dt: 1. / 60.,
+                ..Default::default()
+            });
+        }
+        let state = world.get_state();
+        // The chests are stocked with the rounds of every calibre.
+        let (_, (storage,)) = state.select::<(Storage,)>().next().unwrap();
+        let stored_calibres = storage
+            .stacks()
+            .flat_map(|stack| stack.items())
+            .filter_map(|item| state.select_one::<(Ammo,)>(item))
+            .map(|(ammo,)| ammo.0)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(stored_calibres.len(), 2);
+        // The player starts with spare rounds.
+        let (player, _) = state.select::<(Character,)>().next().unwrap();
+        let spare = state
+            .select_one::<(Equipment,)>(&player)
+            .and_then(|(equipment,)| equipment.get_item_stack(&EquipmentSlot::WeaponAmmo))
+            .map(|stack| stack.items().len());
+        assert_eq!(spare, Some(25));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{physics::ProjectileGenerator, prelude::*};
+
+use super::{Ammo, Calibre, Equipment, EquipmentSlot, Magazine, Storage};
+
+pub trait AmmoInsights {
+    /// Returns the calibre of the rounds fired by the given gun, or `None` if it fires without ammo.
+    fn calibre_of(&self, gun_entity: &EntityRef) -> Option<Calibre>;
+    /// Returns the rounds loaded in the magazine of the given gun.
+    fn loaded_rounds_of(&self, gun_entity: &EntityRef) -> Vec<EntityRef>;
+    /// Returns the rounds in the ammo slot of the `equipment_entity` that can be fired by the given gun.
+    fn spare_rounds_for(
+        &self,
+        equipment_entity: &EntityRef,
+        gun_entity: &EntityRef,
+    ) -> Vec<EntityRef>;
+    /// Returns the round that the gun equipped by the `equipment_entity` fires next.
+    /// The round is taken from the magazine of the gun, or from the ammo slot if the gun has no magazine.
+    fn next_round_for(
+        &self,
+        equipment_entity: &EntityRef,
+        gun_entity: &EntityRef,
+    ) -> Option<EntityRef>;
+    /// Returns true iff the given `round_entity` can be loaded into the magazine of the given gun.
+    fn can_load(&self, gun_entity: &EntityRef, round_entity: &EntityRef) -> bool;
+    /// Returns true iff the given gun is being reloaded.
+    fn is_reloading(&self, gun_entity: &EntityRef) -> bool;
+}
+
+impl<'a, R: StateReader> StateInsights<'a, R> {
+    /// Returns the rounds among the given items that can be fired by the given gun.
+    fn rounds_among<'i>(
+        &self,
+        gun_entity: &EntityRef,
+        items: impl Iterator<Item = &'i EntityRef>,
+    ) -> Vec<EntityRef> {
+        let Some(calibre) = self.calibre_of(gun_entity) else {
+            return vec![];
+        };
+        items
+            // Skip the rounds that were just fired.
+            .filter(|item| !self.0.will_be_removed(item))
+            .filter(|item| {
+                self.0
+                    .select_one::<(Ammo,)>(item)
+                    .map(|(ammo,)| ammo.0 == calibre)
+                    .unwrap_or(false)
+            })
+            .copied()
+            .collect()
+    }
+}
+
+impl<'a, R: StateReader> AmmoInsights for StateInsights<'a, R> {
+    fn calibre_of(&self, gun_entity: &EntityRef) -> Option<Calibre> {
+        self.0
+            .select_one::<(ProjectileGenerator,)>(gun_entity)
+            .and_then(|(p_gen,)| p_gen.calibre)
+    }
+
+    fn loaded_rounds_of(&self, gun_entity: &EntityRef) -> Vec<EntityRef> {
+        self.0
+            .select_one::<(Storage,)>(gun_entity)
+            .map(|(storage,)| {
+                self.rounds_among(gun_entity, storage.stacks().flat_map(|stack| stack.items()))
+            })
+            .unwrap_or_default()
+    }
+
+    fn spare_rounds_for(
+        &self,
+        equipment_entity: &EntityRef,
+        gun_entity: &EntityRef,
+    ) -> Vec<EntityRef> {
+        self.0
+            .select_one::<(Equipment,)>(equipment_entity)
+            .and_then(|(equipment,)| equipment.get_item_stack(&EquipmentSlot::WeaponAmmo))
+            .map(|stack| self.rounds_among(gun_entity, stack.items().iter()))
+            .unwrap_or_default()
+    }
+
+    fn next_round_for(
+        &self,
+        equipment_entity: &EntityRef,
+        gun_entity: &EntityRef,
+    ) -> Option<EntityRef> {
+        if self.0.select_one::<(Magazine,)>(gun_entity).is_some() {
+            self.loaded_rounds_of(gun_entity).into_iter().next()
+        } else {
+            self.spare_rounds_for(equipment_entity, gun_entity)
+                .into_iter()
+                .next()
+        }
+    }
+
+    fn can_load(&self, gun_entity: &EntityRef, round_entity: &EntityRef) -> bool {
+        self.0
+            .select_one::<(Magazine,)>(gun_entity)
+            .map(|(magazine,)| {
+                self.loaded_rounds_of(gun_entity).len() < magazine.capacity
+                    && !self
+                        .rounds_among(gun_entity, std::iter::once(round_entity))
+                        .is_empty()
+            })
+            .unwrap_or(false)
+    }
+
+    fn is_reloading(&self, gun_entity: &EntityRef) -> bool {
+        self.0
+            .select_one::<(InteractTarget<Magazine>,)>(gun_entity)
+            .map(|(intr,)| !intr.actors.is_empty())
+            .unwrap_or(false)
+    }
+}
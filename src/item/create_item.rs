@@ -15,7 +15,11 @@ pub fn create_item(
         item,
         ProximityInteractable,
         InteractTarget::<Item>::default(),
-        Hitbox(HitboxType::Ghost, Shape::Circle { r: 10. }),
+        Hitbox(
+            HitboxType::Ghost,
+            Shape::Circle { r: 10. },
+            CollisionFilter::new(Layers::ITEMS, Layers::CHARACTERS | Layers::SENSORS),
+        ),
         InteractTarget::<Hitbox>::default(),
         Equippable(slots),
         InteractTarget::<VisionField>::default(),
@@ -1,4 +1,6 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
@@ -15,22 +17,47 @@ pub use equipment_slot::*;
 pub use equipment_system::*;
 
 /// An entity that can be equipped by [`Equipment`] entities.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Equippable(pub SlotSelector);
 
 /// An entity that can equip [`Equippable`] entities.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Equipment {
-    slots: HashMap<EquipmentSlot, ItemStack>,
+    /// Ordered, so that the slots are visited & saved in the same order every time.
+    #[serde(with = "slots_as_pairs")]
+    slots: BTreeMap<EquipmentSlot, ItemStack>,
+}
+
+/// (De)serializes the equipment slots as a list of pairs, since not all the slots can be used as map keys.
+mod slots_as_pairs {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{EquipmentSlot, ItemStack};
+
+    pub fn serialize<S: Serializer>(
+        slots: &BTreeMap<EquipmentSlot, ItemStack>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(slots.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<EquipmentSlot, ItemStack>, D::Error> {
+        Vec::<(EquipmentSlot, ItemStack)>::deserialize(deserializer)
+            .map(|pairs| pairs.into_iter().collect())
+    }
 }
 
 impl Equipment {
     pub fn new(accepting_slots: impl IntoIterator<Item = EquipmentSlot>) -> Self {
-        let slots = HashMap::from_iter(
-            accepting_slots
-                .into_iter()
-                .map(|slot| (slot, ItemStack::one())),
-        );
+        let slots = BTreeMap::from_iter(accepting_slots.into_iter().map(|slot| match slot {
+            // Rounds are stacked in the ammo slot.
+            EquipmentSlot::WeaponAmmo => (slot, ItemStack::weighted()),
+            _ => (slot, ItemStack::one()),
+        }));
         Self { slots }
     }
 
@@ -1,9 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{item::ItemStack, prelude::*};
 
 /// Represent a slot in the equipment.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum EquipmentSlot {
     Head,
     Torso,
@@ -39,7 +41,7 @@ impl From<EquipmentSlot> for &'static str {
 }
 
 /// Denotes the slots that an item can occupy.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SlotSelector(Vec<Vec<EquipmentSlot>>);
 
 impl SlotSelector {
@@ -59,7 +61,7 @@ impl SlotSelector {
     pub fn choose_slots<'a>(
         &self,
         item_entity: &EntityRef,
-        slots: &HashMap<EquipmentSlot, ItemStack>,
+        slots: &BTreeMap<EquipmentSlot, ItemStack>,
         state: &impl StateReader,
     ) -> Option<HashSet<EquipmentSlot>> {
         let mut chosen_slots = HashSet::new();
@@ -1,6 +1,7 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
@@ -8,18 +9,18 @@ use super::ItemDescription;
 
 pub const ITEM_STACK_MAX_WEIGHT: f32 = 100.;
 
-/// Represents a stack of item entities.
-#[derive(Clone, Debug)]
+/// Represents a stack of item entities, ordered by their ids.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ItemStack {
     /// Maximum weight this stack can hold.
-    Weighted(f32, HashSet<EntityRef>),
-    One(HashSet<EntityRef>),
+    Weighted(f32, BTreeSet<EntityRef>),
+    One(BTreeSet<EntityRef>),
 }
 
 impl IntoIterator for ItemStack {
     type Item = EntityRef;
 
-    type IntoIter = <HashSet<EntityRef> as IntoIterator>::IntoIter;
+    type IntoIter = <BTreeSet<EntityRef> as IntoIterator>::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
         match self {
@@ -53,13 +54,13 @@ impl ItemStack {
         Self::One(Default::default())
     }
 
-    pub fn items(&self) -> &HashSet<EntityRef> {
+    pub fn items(&self) -> &BTreeSet<EntityRef> {
         match self {
             ItemStack::Weighted(_, items) | ItemStack::One(items) => items,
         }
     }
 
-    pub fn items_mut(&mut self) -> &mut HashSet<EntityRef> {
+    pub fn items_mut(&mut self) -> &mut BTreeSet<EntityRef> {
         match self {
             ItemStack::Weighted(_, items) | ItemStack::One(items) => items,
         }
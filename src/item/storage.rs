@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     item::{ItemDescription, ItemStack},
@@ -16,7 +17,7 @@ pub use storage_insights::*;
 pub use storage_system::*;
 
 /// An entity that can store other entities.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Storage {
     stacks: Vec<ItemStack>,
 }
@@ -42,7 +42,7 @@ pub struct StorageDeactivationSystem;
 impl<R: StateReader> System<R> for StorageDeactivationSystem {
     fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         state
-            .select::<(Storage, InteractTarget<Storage>)>()
+            .select::<(With<Storage>, InteractTarget<Storage>)>()
             .for_each(|(storage_entity, _)| {
                 if let Some(storage_bundle) = state.read_bundle::<StorageBundle>(&storage_entity) {
                     StateInsights::of(state)
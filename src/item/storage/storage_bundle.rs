@@ -10,9 +10,18 @@ pub struct StorageBundle {
 
 impl StorageBundle {
     pub fn create(trans: Transform, cmds: &mut StateCommands) -> Self {
+        // Storages can be pushed around, and slow down by themselves.
         let storage = cmds.create_from((
             trans,
-            Hitbox(HitboxType::Static, Shape::Rect { w: 20., h: 20. }),
+            Hitbox(
+                HitboxType::Dynamic,
+                Shape::Rect { w: 20., h: 20. },
+                CollisionFilter::new(Layers::WALLS, Layers::ALL),
+            ),
+            RigidBody::new(200., 0.2, 0.5),
+            Velocity::default(),
+            TargetVelocity::default(),
+            Acceleration(500.),
             InteractTarget::<Hitbox>::default(),
             InteractTarget::<Storage>::default(),
             Storage::new(60),
@@ -20,10 +29,14 @@ impl StorageBundle {
         ));
         let activator = cmds.create_from((
             Transform::default(),
-            AnchorTransform(storage, (0., 0.), 0.),
+            AnchorTransform((0., 0.), 0.),
             ProximityInteractable,
             UntargetedInteractionDelegate(storage),
-            Hitbox(HitboxType::Ghost, Shape::Rect { w: 40., h: 40. }),
+            Hitbox(
+                HitboxType::Ghost,
+                Shape::Rect { w: 40., h: 40. },
+                CollisionFilter::new(Layers::SENSORS, Layers::CHARACTERS),
+            ),
             InteractTarget::<Hitbox>::default(),
         ));
         cmds.push_bundle(Self { storage, activator })
@@ -1,4 +1,7 @@
-use crate::prelude::*;
+use crate::{
+    item::{AmmoInsights, Magazine},
+    prelude::*,
+};
 
 use super::Storage;
 
@@ -6,6 +9,7 @@ pub trait StorageInsights {
     /// Returns true iff the given entity has a storage.
     fn has_storage(&self, e: &EntityRef) -> bool;
     /// Returns true iff the given `item_entity` can be stored by `storage_entity`.
+    /// Magazines only store the rounds that they can load.
     fn can_store(&self, storage_entity: &EntityRef, item_entity: &EntityRef) -> bool;
     /// Returns true iff the given `item_entity` is being stored by `storage_entity`.
     fn is_storing(&self, storage_entity: &EntityRef, item_entity: &EntityRef) -> bool;
@@ -21,6 +25,8 @@ impl<'a, R: StateReader> StorageInsights for StateInsights<'a, R> {
             .select_one::<(Storage,)>(storage_entity)
             .map(|(storage,)| storage.get_available_slot(item_entity, self.0).is_some())
             .unwrap_or(false)
+            && (self.0.select_one::<(Magazine,)>(storage_entity).is_none()
+                || self.can_load(storage_entity, item_entity))
     }
 
     fn is_storing(&self, storage_entity: &EntityRef, item_entity: &EntityRef) -> bool {
@@ -13,6 +13,7 @@ use camera::*;
 use item::*;
 use physics::*;
 use prelude::*;
+use replay::*;
 use sprite::*;
 use world_gen::*;
 
@@ -23,10 +24,12 @@ mod character;
 mod chunks;
 mod controller;
 mod effects;
+mod headless;
 mod item;
 mod needs;
 mod physics;
 mod prelude;
+mod replay;
 mod sprite;
 mod ui;
 mod vehicle;
@@ -38,6 +41,12 @@ type AssetMap = HashMap<PathBuf, Asset<Texture>>;
 #[derive(notan::AppState)]
 struct AppState {
     world: SystemManager<State>,
+    /// Item transfers requested through the ui, to be applied in the next update.
+    ui_item_transfers: Vec<ItemTransferReq>,
+    /// Records the session if the game is started with `--record <path>`.
+    recorder: Option<Recorder>,
+    /// Replays a session if the game is started with `--replay <path>`.
+    replay: Option<Replay>,
     ui_state: ui::UiState,
     asset_map: AssetMap,
     sprite_representor: SpriteRepresentor,
@@ -63,32 +72,43 @@ fn setup(app: &mut notan::prelude::App, assets: &mut Assets) -> AppState {
             (asset_path, tx)
         })
         .collect();
-    // Generate a debugging world.
-    let mut world = WorldGenerator::generate(WorldTemplate::new([
-        (Transform::at(-40., -40.), PLAYER_TEMPLATE),
-        // (Transform::at(50., 50.), CHEST_TEMPLATE),
-        (Transform::at(500., 500.), BASIC_CAR_TEMPLATE),
-        // (Transform::at(10., 10.), HAND_GUN_TEMPLATE),
-        (Transform::at(10., 10.), MACHINE_GUN_TEMPLATE),
-        (Transform::at(10., 10.), SIMPLE_BACKPACK_TEMPLATE),
-        (Transform::at(10., 10.), RUNNING_SHOES_TEMPLATE),
-        // (Transform::at(-50., -50.), BANDIT_TEMPLATE),
-    ]));
-    let house_size = 4096.;
-    world.update_with(|state, cmds| {
-        HouseGenerator::new("derelict_house").try_generate(
-            &Rect::new((0., 0.), (house_size, house_size)),
-            state,
-            cmds,
-        );
-        // HouseGenerator::new("derelict_house").try_generate(
-        //     &Rect::new((-600., -600.), (house_size, house_size)),
-        //     state,
-        //     cmds,
-        // );
+    // Generate a debugging world, or load the one from the replay.
+    let args = std::env::args().collect_vec();
+    let arg_value = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|idx| args.get(idx + 1))
+    };
+    // Fall back to a new session if the replay can't be loaded.
+    let loaded_replay = arg_value("--replay").and_then(|path| {
+        Replay::load(path)
+            .map_err(|err| eprintln!("could not load the replay {}: {}", path, err))
+            .ok()
+    });
+    let (world, replay) = match loaded_replay {
+        Some((world, replay)) => (world, Some(replay)),
+        None => {
+            let seed = arg_value("--seed")
+                .and_then(|seed| {
+                    seed.parse()
+                        .map_err(|err| eprintln!("could not parse the seed {}: {}", seed, err))
+                        .ok()
+                })
+                .unwrap_or_else(rand::random);
+            (WorldGenerator::generate_debug_world(seed), None)
+        }
+    };
+    // Play without recording if the recording can't be created.
+    let recorder = arg_value("--record").and_then(|path| {
+        Recorder::create(path, &world)
+            .map_err(|err| eprintln!("could not start recording into {}: {}", path, err))
+            .ok()
     });
     AppState {
         world,
+        ui_item_transfers: Default::default(),
+        recorder,
+        replay,
         asset_map,
         ui_state: Default::default(),
         sprite_representor: Default::default(),
@@ -107,9 +127,28 @@ fn update(app: &mut notan::prelude::App, app_state: &mut AppState) {
         app.window().height() as f32,
         app_state.world.get_state(),
     );
+    let live_input = FrameInput {
+        ctx: UpdateContext {
+            dt,
+            control_map,
+            ..Default::default()
+        },
+        item_transfers: std::mem::take(&mut app_state.ui_item_transfers),
+    };
+    // Prefer the replayed input until the replay is over.
+    let input = app_state
+        .replay
+        .as_mut()
+        .and_then(Replay::next_input)
+        .unwrap_or(live_input);
+    if let Some(recorder) = &mut app_state.recorder {
+        if let Err(err) = recorder.record(&input) {
+            eprintln!("stopped recording: {}", err);
+            app_state.recorder = None;
+        }
+    }
     // Update the world with the registered systems.
-    let world = &mut app_state.world;
-    world.update_with_systems(UpdateContext { dt, control_map });
+    input.apply(&mut app_state.world);
 }
 
 fn draw_sprite(
@@ -137,22 +176,28 @@ fn draw_sprite(
 
 fn draw_game(rnd: &mut draw::Draw, app_state: &mut AppState) {
     let game_state = app_state.world.get_state();
-    let draw_bounds = game_state
-        .select::<(Transform, CameraFollow)>()
-        .next()
-        .map(|(_, (trans, camera))| {
-            (
-                (trans.x - camera.w / 2., trans.y - camera.h / 2.),
-                (trans.x + camera.w / 2., trans.y + camera.h / 2.),
-            )
-        })
-        .unwrap_or_default();
+    let draw_bounds = ActiveCamera::bounds(game_state).unwrap_or_default();
+    // Hide what the player cannot see, apart from the stationary entities that were seen before.
+    let visibility = game_state
+        .read_resource::<character::Player>()
+        .zip(ActiveCamera::view_distance(game_state))
+        .and_then(|(player, view_dist)| {
+            StateInsights::of(game_state).visibility_polygon_of(&player.0, view_dist)
+        });
+    let explored = game_state.read_resource::<Explored>();
     game_state
         .select::<(Transform, Sprite)>()
         .filter(|(_, (trans, _))| {
             trans.x.clamp(draw_bounds.0 .0, draw_bounds.1 .0) == trans.x
                 && trans.y.clamp(draw_bounds.0 .1, draw_bounds.1 .1) == trans.y
         })
+        .filter(|(e, (trans, _))| {
+            let Some(visibility) = &visibility else {
+                return true;
+            };
+            explored.map(|ex| ex.0.contains(e)).unwrap_or(false)
+                || visibility.reveals(e, trans, game_state)
+        })
         .flat_map(|(sprite_entity, (trans, sprite))| {
             app_state
                 .sprite_representor
@@ -196,22 +241,75 @@ fn draw_debug(rnd: &mut draw::Draw, state: &impl StateReader) {
                 .position(x, y)
                 .rotate_degrees_from((x, y), -trans.deg)
                 .fill_color(notan::prelude::Color::BLUE);
-            match hitbox.1 {
-                Shape::Circle { r } => {
-                    rnd.circle(r)
+            draw_shape_outline(rnd, &TransformedShape::new(trans, &hitbox.1), color, state);
+        });
+}
+
+/// Draws the outline of the given shape, which is in world coordinates.
+fn draw_shape_outline(
+    rnd: &mut draw::Draw,
+    shape: &TransformedShape,
+    color: notan::prelude::Color,
+    state: &impl StateReader,
+) {
+    let (width, height) = (rnd.width(), rnd.height());
+    let to_screen = |(x, y): (f32, f32)| map_to_screen_cords(x, y, width, height, state);
+    let mut draw_polygon = |points: Vec<(f32, f32)>| {
+        if let Some((&(x, y), rest)) = points.split_first() {
+            let mut path = rnd.path();
+            path.move_to(x, y);
+            rest.iter().for_each(|&(x, y)| {
+                path.line_to(x, y);
+            });
+            path.close().stroke(1.).stroke_color(color);
+        }
+    };
+    match shape {
+        TransformedShape::Circle(circle) => {
+            let (x, y) = to_screen(circle.position);
+            rnd.circle(circle.radius)
+                .position(x, y)
+                .stroke(1.)
+                .stroke_color(color);
+        }
+        TransformedShape::Poly(poly) => {
+            let (x, y) = poly.position;
+            draw_polygon(
+                poly.vertices
+                    .iter()
+                    .map(|(vx, vy)| to_screen((x + vx, y + vy)))
+                    .collect(),
+            );
+        }
+        TransformedShape::Capsule(capsule) => {
+            let (x, y) = capsule.position;
+            let (ax, ay) = capsule.arm();
+            let (px, py) = capsule.perp();
+            draw_polygon(
+                [
+                    (x + ax + px, y + ay + py),
+                    (x - ax + px, y - ay + py),
+                    (x - ax - px, y - ay - py),
+                    (x + ax - px, y + ay - py),
+                ]
+                .into_iter()
+                .map(to_screen)
+                .collect(),
+            );
+            [(x + ax, y + ay), (x - ax, y - ay)]
+                .into_iter()
+                .for_each(|end| {
+                    let (x, y) = to_screen(end);
+                    rnd.circle(capsule.radius)
                         .position(x, y)
                         .stroke(1.)
-                        .rotate_degrees_from((x, y), -trans.deg)
-                        .stroke_color(color);
-                }
-                Shape::Rect { w, h } => {
-                    rnd.rect((x - w / 2., y - h / 2.), (w, h))
-                        .rotate_degrees_from((x, y), -trans.deg)
-                        .stroke(1.)
                         .stroke_color(color);
-                }
-            };
-        });
+                });
+        }
+        TransformedShape::Compound(parts) => parts
+            .iter()
+            .for_each(|part| draw_shape_outline(rnd, part, color, state)),
+    }
 }
 
 fn draw(
@@ -228,15 +326,18 @@ fn draw(
     gfx.render(&game_rnd);
     // Draw the ui
     let egui_rnd = plugins.egui(|ctx| {
-        app_state.world.update_with(|state, egui_cmds| {
-            ui::draw_ui(ctx, state, egui_cmds, &mut app_state.ui_state);
-        });
+        let item_transfers = ui::draw_ui(ctx, app_state.world.get_state(), &mut app_state.ui_state);
+        app_state.ui_item_transfers.extend(item_transfers);
     });
     gfx.render(&egui_rnd);
 }
 
 #[notan::notan_main]
 fn main() -> Result<(), String> {
+    let args = std::env::args().skip(1).collect_vec();
+    if args.first().map(String::as_str) == Some("--headless") {
+        return headless::run(&args[1..]).map_err(|err| err.to_string());
+    }
     notan::init_with(setup)
         .update(update)
         .add_config(notan::egui::EguiConfig)
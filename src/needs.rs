@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum NeedType {
     Health,
     Energy,
@@ -10,10 +12,8 @@ pub enum NeedType {
 }
 
 /// Contains the status of a need.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct NeedStatus {
-    /// Previous value.
-    pub prev: Option<f32>,
     /// Current value.
     pub curr: f32,
     /// The maximum value.
@@ -24,21 +24,13 @@ impl NeedStatus {
     /// Creates a new `NeedStatus` that starts at the maximum value.
     pub fn with_max(max: f32) -> Self {
         assert!(max > 0.);
-        Self {
-            prev: None,
-            curr: max,
-            max,
-        }
+        Self { curr: max, max }
     }
 
     /// Creates a new `NeedStatus` that ends at the maximum value.
     pub fn with_zero(max: f32) -> Self {
         assert!(max > 0.);
-        Self {
-            prev: None,
-            curr: 0.,
-            max,
-        }
+        Self { curr: 0., max }
     }
 
     /// Sets the current status to the maximum value.
@@ -53,21 +45,20 @@ impl NeedStatus {
 
     /// Applies the given change to the status.
     pub fn change(&mut self, delta: &f32) {
-        self.prev = Some(self.curr);
         self.curr += delta;
     }
 
-    /// Returns the previous fraction.
-    pub fn get_prev_frac(&self) -> Option<f32> {
-        Some(self.prev? / self.max)
-    }
-
     /// Returns the fraction, i.e., current value divided by the maximum value.
     pub fn get_fraction(&self) -> f32 {
+        self.fraction_of(self.curr)
+    }
+
+    /// Returns the given value divided by the maximum value.
+    pub fn fraction_of(&self, value: f32) -> f32 {
         if self.max == 0. {
             return 0.;
         }
-        (self.curr as f32) / (self.max as f32)
+        value / self.max
     }
 }
 
@@ -80,7 +71,7 @@ pub enum NeedChange {
     DescendedZero(f32, f32),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Needs(pub Vec<(NeedType, NeedStatus)>);
 
 impl Needs {
@@ -105,54 +96,89 @@ impl Needs {
     }
 }
 
+/// The values of the needs as of the last time their changes were handled by the [`NeedStateSystem`].
+/// Only that system writes them, so that handling the changes of the [`Needs`] doesn't change them again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HandledNeeds(pub Vec<(NeedType, f32)>);
+
+impl HandledNeeds {
+    pub fn get(&self, t: &NeedType) -> Option<f32> {
+        self.0
+            .iter()
+            .find(|(need_type, _)| need_type == t)
+            .map(|(_, value)| *value)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct NeedChangeEvt(EntityRef, NeedType, NeedChange);
 
 /// A system that monitors the state of the needs and outputs the appropriate events when they change.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct NeedStateSystem;
 
 impl<R: StateReader> System<R> for NeedStateSystem {
-    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+    fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         // Handle need status changes...
-        state.select::<(Needs,)>().for_each(|(e, (curr_needs,))| {
-            curr_needs.0.iter().for_each(|(need_type, curr_status)| {
-                // Get the new fraction.
-                let new_frac = curr_status.get_fraction();
-                // Get the old fraction.
-                let old_frac = curr_status.get_prev_frac().unwrap_or(new_frac);
-                // Emit the appropriate increased/decreased event.
-                let need_change = if new_frac > old_frac {
-                    NeedChange::Increased(old_frac, new_frac)
-                } else if new_frac < old_frac {
-                    NeedChange::Decreased(old_frac, new_frac)
-                } else {
-                    return;
-                };
-                let need_type = *need_type;
-                cmds.emit_event(NeedChangeEvt(e, need_type, need_change));
-                // If the need has exceeded the maximum, set the need to maximum and emit the appropriate event.
-                if new_frac > 1. {
-                    let exceeded_change = NeedChange::ExceededMaximum(old_frac, new_frac);
-                    cmds.emit_event(NeedChangeEvt(e, need_type, exceeded_change));
-                    cmds.update_component(&e, move |needs: &mut Needs| {
-                        needs.get_mut(&need_type).map(|need| need.maximize());
-                    });
-                }
-                // If the need has descended zero, set the need to zero and emit the appropriate event.
-                if new_frac < 0. {
-                    let descended_change = NeedChange::DescendedZero(old_frac, new_frac);
-                    cmds.emit_event(NeedChangeEvt(e, need_type, descended_change));
-                    cmds.update_component(&e, move |needs: &mut Needs| {
-                        needs.get_mut(&need_type).map(|need| need.zero());
-                    });
-                }
-            });
-        })
+        state
+            .changed_since::<Needs>(ctx.last_run)
+            .for_each(|(e, curr_needs)| {
+                let handled = state
+                    .select_one::<(HandledNeeds,)>(&e)
+                    .map(|(handled,)| handled);
+                // Remember the handled values, clamped the same way as the statuses themselves below.
+                cmds.set_component(
+                    &e,
+                    HandledNeeds(
+                        curr_needs
+                            .0
+                            .iter()
+                            .map(|(need_type, status)| {
+                                (*need_type, status.curr.clamp(0., status.max))
+                            })
+                            .collect(),
+                    ),
+                );
+                curr_needs.0.iter().for_each(|(need_type, curr_status)| {
+                    let need_type = *need_type;
+                    // Get the new fraction.
+                    let new_frac = curr_status.get_fraction();
+                    // Get the old fraction.
+                    let old_frac = handled
+                        .and_then(|handled| handled.get(&need_type))
+                        .map(|value| curr_status.fraction_of(value))
+                        .unwrap_or(new_frac);
+                    // Emit the appropriate increased/decreased event.
+                    let need_change = if new_frac > old_frac {
+                        NeedChange::Increased(old_frac, new_frac)
+                    } else if new_frac < old_frac {
+                        NeedChange::Decreased(old_frac, new_frac)
+                    } else {
+                        return;
+                    };
+                    cmds.emit_event(NeedChangeEvt(e, need_type, need_change));
+                    // If the need has exceeded the maximum, set the need to maximum and emit the appropriate event.
+                    if new_frac > 1. {
+                        let exceeded_change = NeedChange::ExceededMaximum(old_frac, new_frac);
+                        cmds.emit_event(NeedChangeEvt(e, need_type, exceeded_change));
+                        cmds.update_component(&e, move |needs: &mut Needs| {
+                            needs.get_mut(&need_type).map(|need| need.maximize());
+                        });
+                    }
+                    // If the need has descended zero, set the need to zero and emit the appropriate event.
+                    if new_frac < 0. {
+                        let descended_change = NeedChange::DescendedZero(old_frac, new_frac);
+                        cmds.emit_event(NeedChangeEvt(e, need_type, descended_change));
+                        cmds.update_component(&e, move |needs: &mut Needs| {
+                            needs.get_mut(&need_type).map(|need| need.zero());
+                        });
+                    }
+                });
+            })
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum NeedMutatorEffect {
     /// The given delta will be directly applied to the status.
     Delta(f32),
@@ -160,7 +186,7 @@ pub enum NeedMutatorEffect {
     Rate(f32),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NeedMutator {
     need_type: NeedType,
     effect: NeedMutatorEffect,
@@ -195,3 +221,67 @@ impl<R: StateReader> System<R> for NeedMutatorSystem {
             })
     }
 }
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_need_changes() {
+        let mut world = SystemManager::from(State::default());
+        world.register_system(NeedStateSystem);
+        world.update_with(|_, cmds| {
+            cmds.create_from((Needs::new([(
+                NeedType::Hunger,
+                NeedStatus::with_zero(100.),
+            )]),));
+        });
+        world.update_with_systems(Default::default());
+        // Changes the hunger, returning the reported changes.
+        let change_hunger = |world: &mut SystemManager<State>, delta: f32| {
+            world.update_with(|state, cmds| {
+                let (e, _) = state.select::<(Needs,)>().next().unwrap();
+                cmds.update_component(&e, move |needs: &mut Needs| {
+                    needs.get_mut(&NeedType::Hunger).unwrap().change(&delta);
+                });
+            });
+            world.update_with_systems(Default::default());
+            world
+                .get_state()
+                .read_events::<NeedChangeEvt>()
+                .map(|evt| evt.2)
+                .collect::<Vec<_>>()
+        };
+        assert!(matches!(
+            change_hunger(&mut world, 120.)[..],
+            [
+                NeedChange::Increased(_, _),
+                NeedChange::ExceededMaximum(_, _)
+            ]
+        ));
+        // The clamping of the status is not reported as a decrease.
+        world.update_with_systems(Default::default());
+        assert_eq!(world.get_state().read_events::<NeedChangeEvt>().count(), 0);
+        // The first change after loading a snapshot is still reported.
+        let mut registry = SnapshotRegistry::default();
+        registry.register::<Needs>();
+        registry.register::<HandledNeeds>();
+        let snapshot = world.get_state().to_snapshot(&registry).unwrap();
+        world.set_state(State::from_snapshot(snapshot, &registry).unwrap());
+        assert!(matches!(
+            change_hunger(&mut world, -50.)[..],
+            [NeedChange::Decreased(old, new)] if old == 1. && new == 0.5
+        ));
+        // Handling a change doesn't change the needs again.
+        world.update_with(|state, cmds| {
+            let (e, _) = state.select::<(Needs,)>().next().unwrap();
+            cmds.update_component(&e, |needs: &mut Needs| {
+                needs.get_mut(&NeedType::Hunger).unwrap().change(&-10.);
+            });
+        });
+        let tick = world.get_state().change_tick();
+        world.update_with_systems(Default::default());
+        assert_eq!(world.get_state().read_events::<NeedChangeEvt>().count(), 1);
+        assert_eq!(world.get_state().changed_since::<Needs>(tick).count(), 0);
+    }
+}
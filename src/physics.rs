@@ -2,28 +2,141 @@ use std::collections::HashSet;
 
 use itertools::Itertools;
 use sepax2d::{sat_collision, sat_overlap, Rotate};
+use serde::{Deserialize, Serialize};
 
-use crate::{camera::CameraFollow, prelude::*};
+use crate::prelude::*;
 
+pub use cast_insights::*;
 pub use collider_insights::*;
+pub use exploration::*;
+pub use hearing::*;
+pub use hearing_insights::*;
 pub use projectile::*;
 pub use projectile_insights::*;
+pub use rigid_body::*;
+pub use visibility_polygon::*;
 pub use vision_field::*;
 pub use vision_insights::*;
 
+mod cast_insights;
 mod collider_insights;
+mod exploration;
+mod hearing;
+mod hearing_insights;
 mod projectile;
 mod projectile_insights;
+mod rigid_body;
+mod visibility_polygon;
 mod vision_field;
 mod vision_insights;
 
-#[derive(Clone, Copy, Debug)]
+/// A shape around the center of an entity. Polygons & compound shapes should be built with [`Shape::polygon`] & [`Shape::compound`],
+/// which reject the ones with no area, and loading the invalid ones fails the same way.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "ShapeDef")]
 pub enum Shape {
-    Circle { r: f32 },
-    Rect { w: f32, h: f32 },
+    Circle {
+        r: f32,
+    },
+    Rect {
+        w: f32,
+        h: f32,
+    },
+    /// A convex polygon with the given vertices around its center. Concave vertices are dropped by taking the convex hull.
+    Polygon {
+        vertices: Vec<(f32, f32)>,
+    },
+    /// A rectangle of length `len` along the x-axis, with semicircles of radius `r` on both ends.
+    Capsule {
+        r: f32,
+        len: f32,
+    },
+    /// Several shapes anchored to the same entity.
+    Compound {
+        parts: Vec<(AnchorTransform, Shape)>,
+    },
+}
+
+/// The unchecked form of [`Shape`], which is validated after being deserialized.
+#[derive(Deserialize)]
+enum ShapeDef {
+    Circle {
+        r: f32,
+    },
+    Rect {
+        w: f32,
+        h: f32,
+    },
+    Polygon {
+        vertices: Vec<(f32, f32)>,
+    },
+    Capsule {
+        r: f32,
+        len: f32,
+    },
+    Compound {
+        parts: Vec<(AnchorTransform, Shape)>,
+    },
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl TryFrom<ShapeDef> for Shape {
+    type Error = anyhow::Error;
+
+    fn try_from(def: ShapeDef) -> anyhow::Result<Self> {
+        match def {
+            ShapeDef::Circle { r } => Ok(Shape::Circle { r }),
+            ShapeDef::Rect { w, h } => Ok(Shape::Rect { w, h }),
+            ShapeDef::Polygon { vertices } => Shape::polygon(vertices),
+            ShapeDef::Capsule { r, len } => Ok(Shape::Capsule { r, len }),
+            ShapeDef::Compound { parts } => Shape::compound(parts),
+        }
+    }
+}
+
+impl Shape {
+    /// Returns a convex polygon with the given vertices, or an error if their convex hull has fewer than 3 vertices.
+    pub fn polygon(vertices: Vec<(f32, f32)>) -> anyhow::Result<Self> {
+        if convex_hull(vertices.clone()).len() < 3 {
+            anyhow::bail!(
+                "The polygon {:?} has fewer than 3 vertices off a line",
+                vertices
+            );
+        }
+        Ok(Shape::Polygon { vertices })
+    }
+
+    /// Returns a compound shape with the given parts, or an error if there are none.
+    pub fn compound(parts: Vec<(AnchorTransform, Shape)>) -> anyhow::Result<Self> {
+        if parts.is_empty() {
+            anyhow::bail!("The compound shape has no parts");
+        }
+        Ok(Shape::Compound { parts })
+    }
+
+    /// Returns the axis-aligned bounding box of the shape with the given transform, as its top left & bottom right corners.
+    pub fn aabb(&self, trans: &Transform) -> ((f32, f32), (f32, f32)) {
+        let (hw, hh) = match self {
+            Shape::Circle { r } => (*r, *r),
+            Shape::Rect { w, h } => {
+                let (sin, cos) = trans.deg.to_radians().sin_cos();
+                (
+                    (w / 2. * cos).abs() + (h / 2. * sin).abs(),
+                    (w / 2. * sin).abs() + (h / 2. * cos).abs(),
+                )
+            }
+            _ => return TransformedShape::new(trans, self).aabb(),
+        };
+        ((trans.x - hw, trans.y - hh), (trans.x + hw, trans.y + hh))
+    }
+
+    /// Returns the longest side of the bounding box of the unrotated shape.
+    pub fn extent(&self) -> f32 {
+        let ((x1, y1), (x2, y2)) = self.aabb(&Transform::default());
+        (x2 - x1).max(y2 - y1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HitboxType {
     Ghost,   // not concrete
     Static,  // concrete and static
@@ -36,8 +149,69 @@ impl HitboxType {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct Hitbox(pub HitboxType, pub Shape);
+/// A set of collision layers, as a bitset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Layers(pub u32);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const ALL: Layers = Layers(u32::MAX);
+    pub const WALLS: Layers = Layers(1 << 0);
+    pub const CHARACTERS: Layers = Layers(1 << 1);
+    pub const PROJECTILES: Layers = Layers(1 << 2);
+    pub const SENSORS: Layers = Layers(1 << 3);
+    pub const VEHICLES: Layers = Layers(1 << 4);
+    pub const ITEMS: Layers = Layers(1 << 5);
+
+    pub fn intersects(&self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns these layers without the given ones.
+    pub fn without(&self, other: Layers) -> Layers {
+        Layers(self.0 & !other.0)
+    }
+}
+
+impl std::ops::BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, rhs: Layers) -> Layers {
+        Layers(self.0 | rhs.0)
+    }
+}
+
+/// The layers a hitbox is on, and the layers it collides with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollisionFilter {
+    pub layers: Layers,
+    pub mask: Layers,
+}
+
+impl Default for CollisionFilter {
+    fn default() -> Self {
+        Self::new(Layers::ALL, Layers::ALL)
+    }
+}
+
+impl CollisionFilter {
+    pub fn new(layers: Layers, mask: Layers) -> Self {
+        Self { layers, mask }
+    }
+
+    /// Returns true if the two hitboxes collide with each other, i.e. both of their masks accept the other's layers.
+    pub fn matches(&self, other: &CollisionFilter) -> bool {
+        self.mask.intersects(other.layers) && other.mask.intersects(self.layers)
+    }
+}
+
+/// A hitbox along with its collision filter. Hitboxes saved without a filter are on every layer & collide with every layer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Hitbox(
+    pub HitboxType,
+    pub Shape,
+    #[serde(default)] pub CollisionFilter,
+);
 
 impl Interaction for Hitbox {
     fn priority() -> usize {
@@ -57,6 +231,11 @@ impl Interaction for Hitbox {
     }
 }
 
+/// Keeps the physics simulated within the given radius around the entity, whether the camera sees it or not.
+/// Outside of every zone, the hitboxes that don't move are asleep, and only collide with the awake ones.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SimulationZone(pub f32);
+
 #[derive(Clone, Copy, Debug)]
 pub struct CollisionEvt {
     pub e1: EntityRef,
@@ -69,38 +248,464 @@ pub struct CollisionEvt {
 pub enum TransformedShape {
     Circle(sepax2d::circle::Circle),
     Poly(sepax2d::polygon::Polygon),
+    Capsule(sepax2d::capsule::Capsule),
+    /// The primitive shapes that make up a compound shape. Never contains another compound shape.
+    Compound(Vec<TransformedShape>),
 }
 
+/// The number of halvings when searching for the time of impact of a swept shape.
+const TOI_ITERATIONS: usize = 16;
+
 impl TransformedShape {
-    /// Creates a new transformed shape from the given transform, shape and offset (from the given transform)
+    /// Creates a new transformed shape from the given transform, shape and offset (from the given transform).
+    /// The polygons & compound shapes with no area, which [`Shape::polygon`] & [`Shape::compound`] would reject, are shrunk to a point.
     pub fn new(trans: &Transform, primitive_shape: &Shape) -> Self {
         match primitive_shape {
             Shape::Circle { r } => {
                 Self::Circle(sepax2d::circle::Circle::new((trans.x, trans.y), *r))
             }
-            Shape::Rect { w, h } => {
-                let mut poly = sepax2d::polygon::Polygon::from_vertices(
-                    (0., 0.),
-                    vec![
-                        (-w / 2., -h / 2.),
-                        (w / 2., -h / 2.),
-                        (w / 2., h / 2.),
-                        (-w / 2., h / 2.),
-                    ],
-                );
-                poly.rotate(-trans.deg.to_radians());
-                poly.position = (trans.x, trans.y);
-                Self::Poly(poly)
+            Shape::Rect { w, h } => Self::polygon(
+                trans,
+                vec![
+                    (-w / 2., -h / 2.),
+                    (w / 2., -h / 2.),
+                    (w / 2., h / 2.),
+                    (-w / 2., h / 2.),
+                ],
+            ),
+            Shape::Polygon { vertices } => {
+                let hull = convex_hull(vertices.clone());
+                if hull.len() < 3 {
+                    return Self::point(trans);
+                }
+                Self::polygon(trans, hull)
+            }
+            Shape::Capsule { r, len } => {
+                let (sin, cos) = trans.deg.to_radians().sin_cos();
+                // The y-axis is inverted, just like in the rotation of the polygons.
+                let arm = (len / 2. * cos, -len / 2. * sin);
+                Self::Capsule(sepax2d::capsule::Capsule::new((trans.x, trans.y), arm, *r))
+            }
+            Shape::Compound { parts } if parts.is_empty() => Self::point(trans),
+            Shape::Compound { parts } => Self::Compound(
+                parts
+                    .iter()
+                    .flat_map(|(anchor, shape)| {
+                        Self::new(&anchor.applied_to(trans), shape).parts().to_vec()
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Creates a point at the position of the given transform.
+    fn point(trans: &Transform) -> Self {
+        Self::Circle(sepax2d::circle::Circle::new((trans.x, trans.y), 0.))
+    }
+
+    /// Creates a polygon with the given vertices around the center, rotated & moved by the given transform.
+    fn polygon(trans: &Transform, vertices: Vec<(f32, f32)>) -> Self {
+        let mut poly = sepax2d::polygon::Polygon::from_vertices((0., 0.), vertices);
+        poly.rotate(-trans.deg.to_radians());
+        poly.position = (trans.x, trans.y);
+        Self::Poly(poly)
+    }
+
+    /// Returns the primitive shapes that make up this shape.
+    pub fn parts(&self) -> &[TransformedShape] {
+        match self {
+            Self::Compound(parts) => parts,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// Returns every pair of parts from this shape & the other shape.
+    fn part_pairs<'s>(
+        &'s self,
+        other: &'s TransformedShape,
+    ) -> impl Iterator<Item = (&'s TransformedShape, &'s TransformedShape)> {
+        self.parts().iter().cartesian_product(other.parts().iter())
+    }
+
+    /// Returns true if the shapes overlap.
+    pub fn overlaps(&self, other: &TransformedShape) -> bool {
+        self.part_pairs(other)
+            .any(|(part1, part2)| sat_overlap(part1.shape_ref(), part2.shape_ref()))
+    }
+
+    /// Returns the overlap of the shapes as given by `sat_collision`, or zero if they don't overlap.
+    /// For compound shapes, the deepest overlap among their parts is taken.
+    pub fn collision(&self, other: &TransformedShape) -> (f32, f32) {
+        self.part_pairs(other)
+            .filter(|(part1, part2)| sat_overlap(part1.shape_ref(), part2.shape_ref()))
+            .map(|(part1, part2)| sat_collision(part1.shape_ref(), part2.shape_ref()))
+            .max_by(|(x1, y1), (x2, y2)| (x1 * x1 + y1 * y1).total_cmp(&(x2 * x2 + y2 * y2)))
+            .unwrap_or_default()
+    }
+
+    /// Returns the axis-aligned bounding box of the shape, as its top left & bottom right corners.
+    pub fn aabb(&self) -> ((f32, f32), (f32, f32)) {
+        let merge = |((x1, y1), (x2, y2)): ((f32, f32), (f32, f32)), (vx, vy): (f32, f32)| {
+            ((x1.min(vx), y1.min(vy)), (x2.max(vx), y2.max(vy)))
+        };
+        let empty = (
+            (f32::INFINITY, f32::INFINITY),
+            (f32::NEG_INFINITY, f32::NEG_INFINITY),
+        );
+        match self {
+            Self::Circle(circle) => {
+                let (x, y) = circle.position;
+                let r = circle.radius;
+                ((x - r, y - r), (x + r, y + r))
+            }
+            Self::Poly(poly) => {
+                let (x, y) = poly.position;
+                poly.vertices
+                    .iter()
+                    .fold(empty, |aabb, (vx, vy)| merge(aabb, (x + vx, y + vy)))
+            }
+            Self::Capsule(capsule) => {
+                let (x, y) = capsule.position;
+                let (ax, ay) = capsule.arm();
+                let r = capsule.radius;
+                (
+                    (x - ax.abs() - r, y - ay.abs() - r),
+                    (x + ax.abs() + r, y + ay.abs() + r),
+                )
+            }
+            Self::Compound(parts) => parts.iter().fold(empty, |aabb, part| {
+                let (top_left, btm_right) = part.aabb();
+                merge(merge(aabb, top_left), btm_right)
+            }),
+        }
+    }
+
+    /// Returns the area covered by the shape while it moves by the given amount.
+    /// Swept capsules are approximated by a slightly larger polygon.
+    pub fn swept(&self, motion: (f32, f32)) -> Self {
+        if motion.0 * motion.0 + motion.1 * motion.1 <= f32::EPSILON {
+            return self.clone();
+        }
+        let hull_of = |position: (f32, f32), points: Vec<(f32, f32)>| {
+            let moved = points.iter().map(|(x, y)| (x + motion.0, y + motion.1));
+            let points = points.iter().copied().chain(moved).collect_vec();
+            Self::Poly(sepax2d::polygon::Polygon::from_vertices(
+                position,
+                convex_hull(points),
+            ))
+        };
+        match self {
+            Self::Circle(circle) => {
+                let (x, y) = circle.position;
+                let arm = (motion.0 / 2., motion.1 / 2.);
+                Self::Capsule(sepax2d::capsule::Capsule::new(
+                    (x + arm.0, y + arm.1),
+                    arm,
+                    circle.radius,
+                ))
+            }
+            Self::Poly(poly) => hull_of(poly.position, poly.vertices.clone()),
+            Self::Capsule(capsule) => {
+                // Surround the end circles with octagons.
+                let (ax, ay) = capsule.arm();
+                let r = capsule.radius / std::f32::consts::FRAC_PI_8.cos();
+                let points = [(ax, ay), (-ax, -ay)]
+                    .into_iter()
+                    .flat_map(|(x, y)| {
+                        (0..8).map(move |i| {
+                            let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                            (x + r * angle.cos(), y + r * angle.sin())
+                        })
+                    })
+                    .collect();
+                hull_of(capsule.position, points)
+            }
+            Self::Compound(parts) => {
+                Self::Compound(parts.iter().map(|part| part.swept(motion)).collect())
+            }
+        }
+    }
+
+    /// Returns the fraction of the given motion after which this shape first touches the other shape.
+    /// Returns `None` if they don't touch along the way, and zero if they already overlap.
+    pub fn time_of_impact(&self, motion: (f32, f32), other: &TransformedShape) -> Option<f32> {
+        if matches!(self, Self::Compound(_)) || matches!(other, Self::Compound(_)) {
+            return self
+                .part_pairs(other)
+                .flat_map(|(part1, part2)| part1.time_of_impact(motion, part2))
+                .min_by(f32::total_cmp);
+        }
+        let other = other.shape_ref();
+        if !sat_overlap(self.swept(motion).shape_ref(), other) {
+            return None;
+        }
+        if sat_overlap(self.shape_ref(), other) {
+            return Some(0.);
+        }
+        // The swept area only grows with the fraction of the motion.
+        let (mut lo, mut hi) = (0., 1.);
+        for _ in 0..TOI_ITERATIONS {
+            let mid = (lo + hi) / 2.;
+            let partial_motion = (motion.0 * mid, motion.1 * mid);
+            if sat_overlap(self.swept(partial_motion).shape_ref(), other) {
+                hi = mid;
+            } else {
+                lo = mid;
             }
         }
+        Some(hi)
     }
 
-    pub fn shape_ref(&self) -> &dyn sepax2d::Shape {
+    /// Returns the shape moved by the given amount.
+    pub fn translated(&self, (dx, dy): (f32, f32)) -> Self {
+        let mut shape = self.clone();
+        let position = match &mut shape {
+            Self::Circle(circle) => &mut circle.position,
+            Self::Poly(poly) => &mut poly.position,
+            Self::Capsule(capsule) => &mut capsule.position,
+            Self::Compound(parts) => {
+                return Self::Compound(parts.iter().map(|part| part.translated((dx, dy))).collect())
+            }
+        };
+        *position = (position.0 + dx, position.1 + dy);
+        shape
+    }
+
+    /// Returns the point of this shape that touches the other shape, along with the normal of the other shape at that point.
+    /// The point is taken as the furthest along the direction of the overlap, or along the given fallback direction if they barely touch.
+    /// For compound shapes, the parts that overlap the most are taken, or the closest ones if none of them overlap.
+    pub fn contact(
+        &self,
+        other: &TransformedShape,
+        fallback_dir: (f32, f32),
+    ) -> ((f32, f32), (f32, f32)) {
+        if matches!(self, Self::Compound(_)) || matches!(other, Self::Compound(_)) {
+            let center = |shape: &TransformedShape| {
+                let ((x1, y1), (x2, y2)) = shape.aabb();
+                ((x1 + x2) / 2., (y1 + y2) / 2.)
+            };
+            let depth = |(part1, part2): &(&TransformedShape, &TransformedShape)| {
+                let (x, y) = part1.collision(part2);
+                x * x + y * y
+            };
+            let dist = |(part1, part2): &(&TransformedShape, &TransformedShape)| {
+                let ((x1, y1), (x2, y2)) = (center(part1), center(part2));
+                (x1 - x2).powi(2) + (y1 - y2).powi(2)
+            };
+            let closest = self
+                .part_pairs(other)
+                .filter(|pair| depth(pair) > 0.)
+                .max_by(|pair1, pair2| depth(pair1).total_cmp(&depth(pair2)))
+                .or_else(|| {
+                    self.part_pairs(other)
+                        .min_by(|pair1, pair2| dist(pair1).total_cmp(&dist(pair2)))
+                });
+            return closest
+                .map(|(part1, part2)| part1.contact(part2, fallback_dir))
+                .unwrap_or_default();
+        }
+        let overlap = sat_collision(self.shape_ref(), other.shape_ref());
+        let dir = notan::math::vec2(overlap.0, overlap.1)
+            .try_normalize()
+            .or(notan::math::vec2(fallback_dir.0, fallback_dir.1).try_normalize())
+            .unwrap_or_default();
+        let normal = (-dir.x, -dir.y);
+        let furthest = |position: (f32, f32), points: &[(f32, f32)]| {
+            let (x, y) = points
+                .iter()
+                .copied()
+                .max_by(|p1, p2| {
+                    (p1.0 * dir.x + p1.1 * dir.y).total_cmp(&(p2.0 * dir.x + p2.1 * dir.y))
+                })
+                .unwrap_or_default();
+            (position.0 + x, position.1 + y)
+        };
+        let point = match self {
+            Self::Circle(circle) => (
+                circle.position.0 + dir.x * circle.radius,
+                circle.position.1 + dir.y * circle.radius,
+            ),
+            Self::Poly(poly) => furthest(poly.position, &poly.vertices),
+            Self::Capsule(capsule) => {
+                let (ax, ay) = capsule.arm();
+                let (x, y) = furthest(capsule.position, &[(ax, ay), (-ax, -ay)]);
+                (x + dir.x * capsule.radius, y + dir.y * capsule.radius)
+            }
+            Self::Compound(_) => unreachable!(),
+        };
+        (point, normal)
+    }
+
+    /// Returns the distance along the ray at which it enters the shape, along with the normal of the shape at that point.
+    /// The direction should be normalized. Rays starting inside the shape hit it right away, against their direction.
+    pub fn raycast(
+        &self,
+        origin: (f32, f32),
+        dir: (f32, f32),
+        max_dist: f32,
+    ) -> Option<(f32, (f32, f32))> {
+        match self {
+            Self::Circle(circle) => {
+                ray_circle(origin, dir, max_dist, circle.position, circle.radius)
+            }
+            Self::Poly(poly) => {
+                let (x, y) = poly.position;
+                let vertices = poly.vertices.iter().map(|(vx, vy)| (x + vx, y + vy));
+                ray_convex(origin, dir, max_dist, &vertices.collect_vec())
+            }
+            Self::Capsule(capsule) => {
+                // The capsule is the union of a rectangle & the circles at its ends.
+                let (x, y) = capsule.position;
+                let (ax, ay) = capsule.arm();
+                let (px, py) = capsule.perp();
+                let rect = [
+                    (x + ax + px, y + ay + py),
+                    (x - ax + px, y - ay + py),
+                    (x - ax - px, y - ay - py),
+                    (x + ax - px, y + ay - py),
+                ];
+                [
+                    ray_circle(origin, dir, max_dist, (x + ax, y + ay), capsule.radius),
+                    ray_circle(origin, dir, max_dist, (x - ax, y - ay), capsule.radius),
+                    ray_convex(origin, dir, max_dist, &rect),
+                ]
+                .into_iter()
+                .flatten()
+                .min_by(|(dist1, _), (dist2, _)| dist1.total_cmp(dist2))
+            }
+            Self::Compound(parts) => parts
+                .iter()
+                .flat_map(|part| part.raycast(origin, dir, max_dist))
+                .min_by(|(dist1, _), (dist2, _)| dist1.total_cmp(dist2)),
+        }
+    }
+
+    /// Returns the underlying `sepax2d` shape. Compound shapes must be split into their [`parts`](Self::parts) first.
+    fn shape_ref(&self) -> &dyn sepax2d::Shape {
         match self {
             Self::Circle(shape) => shape,
             Self::Poly(shape) => shape,
+            Self::Capsule(shape) => shape,
+            Self::Compound(_) => unreachable!("compound shapes have no single sepax2d shape"),
+        }
+    }
+}
+
+/// Returns where the ray enters the circle, see [`TransformedShape::raycast`].
+fn ray_circle(
+    origin: (f32, f32),
+    dir: (f32, f32),
+    max_dist: f32,
+    center: (f32, f32),
+    r: f32,
+) -> Option<(f32, (f32, f32))> {
+    let m = (origin.0 - center.0, origin.1 - center.1);
+    let c = m.0 * m.0 + m.1 * m.1 - r * r;
+    if c <= 0. {
+        return Some((0., (-dir.0, -dir.1)));
+    }
+    let b = m.0 * dir.0 + m.1 * dir.1;
+    let disc = b * b - c;
+    // Missing the circle, or moving away from it.
+    if disc < 0. || b > 0. {
+        return None;
+    }
+    let dist = -b - disc.sqrt();
+    if dist > max_dist {
+        return None;
+    }
+    let normal = notan::math::vec2(m.0 + dir.0 * dist, m.1 + dir.1 * dist).normalize_or_zero();
+    Some((dist, (normal.x, normal.y)))
+}
+
+/// Returns where the ray enters the convex polygon with the given vertices, see [`TransformedShape::raycast`].
+fn ray_convex(
+    origin: (f32, f32),
+    dir: (f32, f32),
+    max_dist: f32,
+    vertices: &[(f32, f32)],
+) -> Option<(f32, (f32, f32))> {
+    let n = vertices.len() as f32;
+    let center = vertices
+        .iter()
+        .fold((0., 0.), |(cx, cy), (x, y)| (cx + x / n, cy + y / n));
+    // Clip the ray against the half-planes of the edges.
+    let (mut enter, mut exit) = (0., max_dist);
+    let mut enter_normal = None;
+    for (a, b) in vertices.iter().circular_tuple_windows() {
+        let mut normal = (b.1 - a.1, a.0 - b.0);
+        if normal.0 * (a.0 - center.0) + normal.1 * (a.1 - center.1) < 0. {
+            normal = (-normal.0, -normal.1);
+        }
+        let dist_to_edge = normal.0 * (a.0 - origin.0) + normal.1 * (a.1 - origin.1);
+        let speed = normal.0 * dir.0 + normal.1 * dir.1;
+        if speed == 0. {
+            if dist_to_edge < 0. {
+                return None;
+            }
+            continue;
+        }
+        let dist = dist_to_edge / speed;
+        if speed < 0. {
+            if dist > enter {
+                enter = dist;
+                enter_normal = Some(normal);
+            }
+        } else {
+            exit = exit.min(dist);
+        }
+        if enter > exit {
+            return None;
+        }
+    }
+    let normal = enter_normal
+        .map(|(x, y)| notan::math::vec2(x, y).normalize_or_zero())
+        .unwrap_or(notan::math::vec2(-dir.0, -dir.1));
+    Some((enter, (normal.x, normal.y)))
+}
+
+/// Returns the convex hull of the given points, in counter-clockwise order.
+fn convex_hull(mut points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    points.sort_by(|p1, p2| p1.0.total_cmp(&p2.0).then(p1.1.total_cmp(&p2.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    // Build the lower & the upper halves of the hull with Andrew's monotone chain.
+    let mut hull: Vec<(f32, f32)> = Vec::with_capacity(points.len() + 1);
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(p);
         }
+        // The last point is the first point of the next half.
+        hull.pop();
     }
+    hull
+}
+
+/// Returns the pairs of the given bounding boxes that overlap, as their indices.
+/// The pairs are sorted, and the lower index comes first in each of them.
+fn overlapping_pairs(aabbs: impl Iterator<Item = ((f32, f32), (f32, f32))>) -> Vec<(usize, usize)> {
+    let mut aabbs = aabbs
+        .enumerate()
+        .map(|(i, ((x1, y1), (x2, y2)))| (broccoli::rect(x1, x2, y1, y2), i))
+        .collect_vec();
+    let mut pairs = vec![];
+    broccoli::Tree::new(&mut aabbs).find_colliding_pairs(|a, b| {
+        let (i, j) = (*a.unpack_inner(), *b.unpack_inner());
+        pairs.push((i.min(j), i.max(j)));
+    });
+    pairs.sort_unstable();
+    pairs
 }
 
 /// Represents a collider that should be checked against collisions.
@@ -149,11 +754,11 @@ impl CollisionDetectionSystem {
         ehb1: &EffectiveHitbox,
         ehb2: &EffectiveHitbox,
     ) -> Option<CollisionResponse> {
-        if !sat_overlap(ehb1.shape.shape_ref(), ehb2.shape.shape_ref()) {
+        if !ehb1.shape.overlaps(&ehb2.shape) {
             return None;
         }
         // Get the collision response.
-        let (resp_x, resp_y) = sat_collision(ehb1.shape.shape_ref(), ehb2.shape.shape_ref());
+        let (resp_x, resp_y) = ehb1.shape.collision(&ehb2.shape);
         let resp = notan::math::vec2(resp_x, resp_y);
         Some(CollisionResponse {
             e1: ehb1.entity,
@@ -162,87 +767,117 @@ impl CollisionDetectionSystem {
         })
     }
 
-    fn separate_collision(
-        e1: &EntityRef,
-        e2: &EntityRef,
-        overlap: &(f32, f32),
+    /// Returns the given entity as a body that responds to its collision with the other entity.
+    /// Returns `None` for the ghost hitboxes, which don't respond at all.
+    fn contact_body(
+        e: &EntityRef,
+        other: &EntityRef,
         state: &impl StateReader,
-        cmds: &mut StateCommands,
-    ) {
-        let anchored = StateInsights::of(state).anchor_parent_of(e1).is_some()
+    ) -> Option<ContactBody> {
+        let (hb, vel, rb) =
+            state.select_one::<(Hitbox, Optional<Velocity>, Optional<RigidBody>)>(e)?;
+        if hb.0 == HitboxType::Ghost {
+            return None;
+        }
+        // The anchored entities, and the parents of the anchored entities they collide with, stay in place.
+        let anchored = StateInsights::of(state).anchor_parent_of(e).is_some()
             || StateInsights::of(state)
-                .anchor_parent_of(&e2)
-                .map(|parent| parent == e1)
+                .anchor_parent_of(other)
+                .map(|parent| parent == e)
                 .unwrap_or(false);
-        if anchored {
+        let body = rb.copied().unwrap_or_default();
+        let inv_mass = if hb.0 == HitboxType::Dynamic && !anchored && body.mass > 0. {
+            1. / body.mass
+        } else {
+            0.
+        };
+        Some(ContactBody {
+            inv_mass,
+            vel: vel.map(|vel| (vel.x, vel.y)).unwrap_or_default(),
+            body,
+        })
+    }
+
+    /// Pushes the colliding entities apart in proportion to their inverse masses, and applies the collision impulse to their velocities.
+    fn respond_to_collision(
+        resp: &CollisionResponse,
+        state: &impl StateReader,
+        cmds: &mut StateCommands,
+    ) {
+        let (Some(b1), Some(b2)) = (
+            Self::contact_body(&resp.e1, &resp.e2, state),
+            Self::contact_body(&resp.e2, &resp.e1, state),
+        ) else {
+            return;
+        };
+        let inv_mass_sum = b1.inv_mass + b2.inv_mass;
+        if inv_mass_sum <= 0. {
             return;
         }
-        if let Some((hb,)) = state.select_one::<(Hitbox,)>(e1) {
-            let mut dpos = notan::math::vec2(-overlap.0, -overlap.1);
-            // who cares ??
-            if dpos.length_squared() <= 1. {
-                return;
-            }
-            if let Some((other_hb,)) = state.select_one::<(Hitbox,)>(e2) {
-                if hb.0 != HitboxType::Dynamic || other_hb.0 == HitboxType::Ghost {
-                    return;
-                }
-                // this = dynamic, other = dynamic | static
-                if other_hb.0 == HitboxType::Dynamic {
-                    dpos *= 0.5;
-                }
-                cmds.update_component(e1, move |trans: &mut Transform| {
-                    trans.x += dpos.x;
-                    trans.y += dpos.y;
+        let overlap = notan::math::vec2(resp.overlap.0, resp.overlap.1);
+        // who cares ??
+        if overlap.length_squared() > 1. {
+            let dpos1 = -overlap * b1.inv_mass / inv_mass_sum;
+            let dpos2 = overlap * b2.inv_mass / inv_mass_sum;
+            [(resp.e1, dpos1), (resp.e2, dpos2)]
+                .into_iter()
+                .filter(|(_, dpos)| *dpos != notan::math::Vec2::ZERO)
+                .for_each(|(e, dpos)| {
+                    cmds.update_component(&e, move |trans: &mut Transform| {
+                        trans.x += dpos.x;
+                        trans.y += dpos.y;
+                    });
                 });
-                // Reset the velocity.
-                if other_hb.0 == HitboxType::Static {
-                    cmds.set_component(e1, Velocity::default());
-                    // cmds.set_component(e1, TargetVelocity::default());
-                }
-            }
         }
+        let normal = overlap.normalize_or_zero();
+        let (dvel1, dvel2) = collision_impulse(&b1, &b2, (normal.x, normal.y));
+        [(resp.e1, dvel1), (resp.e2, dvel2)]
+            .into_iter()
+            .filter(|(_, dvel)| *dvel != (0., 0.))
+            .for_each(|(e, dvel)| {
+                cmds.update_component(&e, move |vel: &mut Velocity| {
+                    vel.x += dvel.0;
+                    vel.y += dvel.1;
+                });
+            });
     }
 }
 
 impl<R: StateReader> System<R> for CollisionDetectionSystem {
     fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
-        let collision_bounds = state
-            .select::<(Transform, CameraFollow)>()
-            .next()
-            .map(|(_, (trans, camera))| {
-                (
-                    (trans.x - camera.w / 2., trans.y - camera.h / 2.),
-                    (trans.x + camera.w / 2., trans.y + camera.h / 2.),
-                )
-            })
-            .unwrap_or_default();
+        let insights = StateInsights::of(state);
+        let in_zones: HashSet<_> = state
+            .select::<(Transform, SimulationZone)>()
+            .flat_map(|(_, (trans, zone))| insights.within_radius((trans.x, trans.y), zone.0))
+            .collect();
+        let mut awake = HashSet::new();
         let effective_hbs = state
-            .select::<(Transform, Hitbox)>()
-            .filter(|(_, (trans, _))| {
-                trans.x.clamp(collision_bounds.0 .0, collision_bounds.1 .0) == trans.x
-                    && trans.y.clamp(collision_bounds.0 .1, collision_bounds.1 .1) == trans.y
-            })
-            .flat_map(|(e, _)| {
-                if let Some(_) = state.select_one::<(Velocity,)>(&e) {
+            .select::<(Transform, Hitbox, Optional<Velocity>)>()
+            .flat_map(|(e, (_, _, vel))| {
+                let is_moving = vel.map(|vel| vel.x != 0. || vel.y != 0.).unwrap_or(false);
+                if is_moving || in_zones.contains(&e) {
+                    awake.insert(e);
+                }
+                if vel.is_some() {
                     EffectiveHitbox::new_speculative(&e, ctx.dt, state)
                 } else {
                     EffectiveHitbox::new(&e, state)
                 }
             })
             .collect_vec();
-        let resps = effective_hbs
+        // The sleeping hitboxes are only checked against the awake ones.
+        let resps = overlapping_pairs(effective_hbs.iter().map(|ehb| ehb.shape.aabb()))
             .into_iter()
-            .tuple_combinations()
+            .map(|(i, j)| (&effective_hbs[i], &effective_hbs[j]))
+            .filter(|(ehb1, ehb2)| awake.contains(&ehb1.entity) || awake.contains(&ehb2.entity))
+            .filter(|(ehb1, ehb2)| ehb1.hitbox.2.matches(&ehb2.hitbox.2))
             .filter(|(ehb1, _)| ehb1.hitbox.0 != HitboxType::Static)
-            .flat_map(|(ehb1, ehb2)| Self::resolve_collision(&ehb1, &ehb2))
+            .flat_map(|(ehb1, ehb2)| Self::resolve_collision(ehb1, ehb2))
             .collect_vec();
         // Separate the colliding pairs.
-        resps.iter().for_each(|resp| {
-            let neg_overlap = (-resp.overlap.0, -resp.overlap.1);
-            Self::separate_collision(&resp.e1, &resp.e2, &resp.overlap, state, cmds);
-            Self::separate_collision(&resp.e2, &resp.e1, &neg_overlap, state, cmds);
-        });
+        resps
+            .iter()
+            .for_each(|resp| Self::respond_to_collision(resp, state, cmds));
         // Generate the new pair of collisions.
         let colliding_pairs: HashSet<_> = resps.iter().map(|resp| (resp.e1, resp.e2)).collect();
         // Handle the hitbox interaction.
@@ -253,6 +888,8 @@ impl<R: StateReader> System<R> for CollisionDetectionSystem {
                 hb_intr.actors.iter().map(move |actor| (*actor, target))
             })
             .unique()
+            // The sleeping pairs keep on interacting until one of them wakes up.
+            .filter(|(actor, target)| awake.contains(actor) || awake.contains(target))
             .for_each(|(actor, target)| {
                 if !colliding_pairs.contains(&(actor, target))
                     && !colliding_pairs.contains(&(target, actor))
@@ -267,4 +904,127 @@ impl<R: StateReader> System<R> for CollisionDetectionSystem {
             }
         });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[test]
+    fn test_time_of_impact() {
+        let wall = TransformedShape::new(&Transform::at(50., 0.), &Shape::Rect { w: 30., h: 100. });
+        let bullet = TransformedShape::new(&Transform::at(0., 0.), &Shape::Circle { r: 5. });
+        // The bullet would end up past the wall, so only the sweep sees the impact.
+        let motion = (100., 0.);
+        assert!(!sat_overlap(
+            bullet.translated(motion).shape_ref(),
+            wall.shape_ref()
+        ));
+        let toi = bullet.time_of_impact(motion, &wall).unwrap();
+        assert!((toi - 0.3).abs() < 1e-3);
+        let ((x, y), normal) = bullet
+            .translated((motion.0 * toi, 0.))
+            .contact(&wall, motion);
+        assert!((x - 35.).abs() < 0.1 && y.abs() < 0.1);
+        assert_eq!(normal, (-1., 0.));
+        // The swept rectangles hit at the same time.
+        let block = TransformedShape::new(&Transform::at(0., 0.), &Shape::Rect { w: 10., h: 10. });
+        let toi = block.time_of_impact(motion, &wall).unwrap();
+        assert!((toi - 0.3).abs() < 1e-3);
+        // Passing by the wall.
+        let bullet = bullet.translated((0., 60.));
+        assert_eq!(bullet.time_of_impact(motion, &wall), None);
+    }
+
+    #[test]
+    fn test_shapes() {
+        // A wall with a door gap in the middle.
+        let wall = Shape::compound(vec![
+            (
+                AnchorTransform((-40., 0.), 0.),
+                Shape::Rect { w: 40., h: 10. },
+            ),
+            (
+                AnchorTransform((40., 0.), 0.),
+                Shape::Rect { w: 40., h: 10. },
+            ),
+        ])
+        .unwrap();
+        let wall = TransformedShape::new(&Transform::at(0., 0.).with_deg(90.), &wall);
+        assert_eq!(wall.parts().len(), 2);
+        let ((x1, y1), (x2, y2)) = wall.aabb();
+        assert!((x1 + 5.).abs() < 1e-3 && (x2 - 5.).abs() < 1e-3);
+        assert!((y1 + 60.).abs() < 1e-3 && (y2 - 60.).abs() < 1e-3);
+        // Passing through the door.
+        let ball = TransformedShape::new(&Transform::at(-50., 0.), &Shape::Circle { r: 5. });
+        assert_eq!(ball.time_of_impact((100., 0.), &wall), None);
+        // Hitting the lower part of the wall.
+        let ball = ball.translated((0., 40.));
+        let toi = ball.time_of_impact((100., 0.), &wall).unwrap();
+        assert!((toi - 0.4).abs() < 1e-3);
+        let (dx, dy) = ball.translated((42., 0.)).collision(&wall);
+        assert!((dx.abs() - 2.).abs() < 1e-3 && dy.abs() < 1e-3);
+        // A capsule rotated upright.
+        let capsule = TransformedShape::new(
+            &Transform::at(0., 0.).with_deg(90.),
+            &Shape::Capsule { r: 5., len: 20. },
+        );
+        let ((x1, y1), (x2, y2)) = capsule.aabb();
+        assert!((x2 - x1 - 10.).abs() < 1e-3 && (y2 - y1 - 30.).abs() < 1e-3);
+        let (dist, _) = capsule.raycast((0., -50.), (0., 1.), 100.).unwrap();
+        assert!((dist - 35.).abs() < 1e-3);
+        // The concave vertex of the polygon is dropped.
+        let triangle = Shape::polygon(vec![(0., -10.), (10., 10.), (0., 0.), (-10., 10.)]).unwrap();
+        assert_eq!(triangle.extent(), 20.);
+        let triangle = TransformedShape::new(&Transform::at(0., 0.), &triangle);
+        assert!(triangle.overlaps(&TransformedShape::new(
+            &Transform::at(0., 5.),
+            &Shape::Circle { r: 1. }
+        )));
+        // The shapes with no area are rejected, whether they are built or loaded.
+        assert!(Shape::polygon(vec![]).is_err());
+        assert!(Shape::polygon(vec![(0., 0.), (10., 10.), (20., 20.)]).is_err());
+        assert!(Shape::compound(vec![]).is_err());
+        let line = Shape::Polygon {
+            vertices: vec![(-10., 0.), (10., 0.)],
+        };
+        let ((x1, y1), (x2, y2)) = TransformedShape::new(&Transform::at(5., 5.), &line).aabb();
+        assert_eq!((x1, y1, x2, y2), (5., 5., 5., 5.));
+        let polygon = r#"{"Polygon": {"vertices": [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]]}}"#;
+        let line = r#"{"Polygon": {"vertices": [[0.0, 0.0], [10.0, 0.0]]}}"#;
+        let compound_of = |parts: &[&str]| {
+            let parts = parts
+                .iter()
+                .map(|part| format!("[[[0.0, 0.0], 0.0], {}]", part))
+                .join(", ");
+            format!(r#"{{"Compound": {{"parts": [{}]}}}}"#, parts)
+        };
+        assert!(serde_json::from_str::<Shape>(polygon).is_ok());
+        assert!(serde_json::from_str::<Shape>(&compound_of(&[polygon])).is_ok());
+        assert!(serde_json::from_str::<Shape>(line).is_err());
+        assert!(serde_json::from_str::<Shape>(&compound_of(&[polygon, line])).is_err());
+        assert!(serde_json::from_str::<Shape>(&compound_of(&[&compound_of(&[])])).is_err());
+    }
+
+    #[test]
+    fn test_collision_filter() {
+        let wall = CollisionFilter::new(Layers::WALLS, Layers::ALL);
+        let bullet = CollisionFilter::new(Layers::PROJECTILES, Layers::WALLS | Layers::CHARACTERS);
+        let sensor = CollisionFilter::new(Layers::SENSORS, Layers::ALL.without(Layers::SENSORS));
+        assert!(wall.matches(&bullet) && bullet.matches(&wall));
+        assert!(wall.matches(&sensor));
+        // Both masks must accept the other's layers.
+        assert!(!bullet.matches(&sensor) && !sensor.matches(&bullet));
+        assert!(!sensor.matches(&sensor));
+        // The hitboxes saved before the filters collide with everything.
+        let hitbox: Hitbox = serde_json::from_str(r#"["Static", {"Circle": {"r": 5.0}}]"#).unwrap();
+        assert_eq!(hitbox.2, CollisionFilter::default());
+        assert!(hitbox.2.matches(&bullet));
+    }
 }
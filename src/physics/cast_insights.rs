@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::prelude::*;
+
+use super::{Hitbox, Shape, TransformedShape};
+
+/// The first hitbox met by a ray or a moving shape.
+#[derive(Clone, Copy, Debug)]
+pub struct CastHit {
+    pub entity: EntityRef,
+    /// The point where the cast touches the hitbox.
+    pub point: (f32, f32),
+    /// The normal of the hitbox at the point, pointing out of the hitbox.
+    pub normal: (f32, f32),
+    /// The distance travelled along the cast before the hit.
+    pub dist: f32,
+}
+
+/// Provides insights about what lies along a line, through casting rays or shapes against the hitboxes.
+pub trait CastInsights<'a> {
+    /// Returns the first hitbox accepted by the filter along the ray, within the given distance.
+    /// Rays starting inside a hitbox hit it right away.
+    fn raycast(
+        &self,
+        origin: (f32, f32),
+        dir: (f32, f32),
+        max_dist: f32,
+        filter: impl Fn(&EntityRef, &Hitbox) -> bool,
+    ) -> Option<CastHit>;
+    /// Returns the first hitbox accepted by the filter that the shape touches when moved along the given direction, within the given distance.
+    /// The distance is the one travelled by the shape before the hit.
+    fn shape_cast(
+        &self,
+        shape: &Shape,
+        from: &Transform,
+        dir: (f32, f32),
+        max_dist: f32,
+        filter: impl Fn(&EntityRef, &Hitbox) -> bool,
+    ) -> Option<CastHit>;
+}
+
+/// The size of the square cells of the [`HitboxGrid`].
+const CELL_SIZE: f32 = 128.;
+/// The hitboxes covering more cells than this are kept aside, and checked by every query.
+const MAX_CELLS_PER_HITBOX: i32 = 64;
+
+/// A uniform grid over the bounding boxes of the hitboxes, which narrows down the hitboxes checked by the casts.
+/// Derived from the state once per update, see [`StateReader::derived`].
+#[derive(Clone, Debug, Default)]
+struct HitboxGrid {
+    /// The hitboxes whose bounding boxes overlap with each non-empty cell.
+    cells: HashMap<(i32, i32), Vec<EntityRef>>,
+    /// The hitboxes that cover too many cells.
+    large: Vec<EntityRef>,
+}
+
+impl HitboxGrid {
+    fn of(state: &impl StateReader) -> Self {
+        let mut grid = Self::default();
+        state
+            .select::<(Hitbox, Transform)>()
+            .for_each(|(e, (hitbox, trans))| {
+                let (min_cell, max_cell) = Self::cells_of(hitbox.1.aabb(trans));
+                if (max_cell.0 - min_cell.0 + 1) * (max_cell.1 - min_cell.1 + 1)
+                    > MAX_CELLS_PER_HITBOX
+                {
+                    grid.large.push(e);
+                    return;
+                }
+                for y in min_cell.1..=max_cell.1 {
+                    for x in min_cell.0..=max_cell.0 {
+                        grid.cells.entry((x, y)).or_default().push(e);
+                    }
+                }
+            });
+        grid
+    }
+
+    /// Returns the first & the last cells covered by the given bounding box.
+    fn cells_of(((x1, y1), (x2, y2)): ((f32, f32), (f32, f32))) -> ((i32, i32), (i32, i32)) {
+        let cell_of = |x: f32, y: f32| {
+            (
+                (x / CELL_SIZE).floor() as i32,
+                (y / CELL_SIZE).floor() as i32,
+            )
+        };
+        (cell_of(x1, y1), cell_of(x2, y2))
+    }
+
+    /// Returns the hitboxes in the cells covered by the given bounding box, sorted by their ids.
+    fn candidates_within(&self, bounds: ((f32, f32), (f32, f32))) -> Vec<EntityRef> {
+        let (min_cell, max_cell) = Self::cells_of(bounds);
+        (min_cell.1..=max_cell.1)
+            .flat_map(|y| (min_cell.0..=max_cell.0).map(move |x| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .chain(self.large.iter())
+            .copied()
+            .sorted_by_key(|e| e.id())
+            .dedup()
+            .collect()
+    }
+}
+
+impl<'a, R: StateReader> StateInsights<'a, R> {
+    /// Returns the hitboxes accepted by the filter whose bounding boxes overlap with the given one, in the order of their ids.
+    pub(super) fn hitboxes_within<'f>(
+        &self,
+        bounds: ((f32, f32), (f32, f32)),
+        filter: impl Fn(&EntityRef, &Hitbox) -> bool + 'f,
+    ) -> impl Iterator<Item = (EntityRef, TransformedShape)> + 'f
+    where
+        'a: 'f,
+    {
+        let state = self.0;
+        let ((x1, y1), (x2, y2)) = bounds;
+        state
+            .derived(HitboxGrid::of)
+            .candidates_within(bounds)
+            .into_iter()
+            .filter_map(move |e| {
+                let (hitbox, trans) = state.select_one::<(Hitbox, Transform)>(&e)?;
+                let ((hx1, hy1), (hx2, hy2)) = hitbox.1.aabb(trans);
+                let overlaps = hx1 <= x2 && x1 <= hx2 && hy1 <= y2 && y1 <= hy2;
+                (overlaps && filter(&e, hitbox))
+                    .then(|| (e, TransformedShape::new(trans, &hitbox.1)))
+            })
+    }
+}
+
+impl<'a, R: StateReader> CastInsights<'a> for StateInsights<'a, R> {
+    fn raycast(
+        &self,
+        origin: (f32, f32),
+        dir: (f32, f32),
+        max_dist: f32,
+        filter: impl Fn(&EntityRef, &Hitbox) -> bool,
+    ) -> Option<CastHit> {
+        let dir = notan::math::vec2(dir.0, dir.1).try_normalize()?;
+        let end = (origin.0 + dir.x * max_dist, origin.1 + dir.y * max_dist);
+        let bounds = (
+            (origin.0.min(end.0), origin.1.min(end.1)),
+            (origin.0.max(end.0), origin.1.max(end.1)),
+        );
+        self.hitboxes_within(bounds, filter)
+            .filter_map(|(e, shape)| {
+                let (dist, normal) = shape.raycast(origin, (dir.x, dir.y), max_dist)?;
+                Some(CastHit {
+                    entity: e,
+                    point: (origin.0 + dir.x * dist, origin.1 + dir.y * dist),
+                    normal,
+                    dist,
+                })
+            })
+            .min_by(|hit1, hit2| hit1.dist.total_cmp(&hit2.dist))
+    }
+
+    fn shape_cast(
+        &self,
+        shape: &Shape,
+        from: &Transform,
+        dir: (f32, f32),
+        max_dist: f32,
+        filter: impl Fn(&EntityRef, &Hitbox) -> bool,
+    ) -> Option<CastHit> {
+        let dir = notan::math::vec2(dir.0, dir.1).try_normalize()?;
+        let motion = (dir.x * max_dist, dir.y * max_dist);
+        let moving = TransformedShape::new(from, shape);
+        let bounds = moving.swept(motion).aabb();
+        self.hitboxes_within(bounds, filter)
+            .filter_map(|(e, other)| {
+                let toi = moving.time_of_impact(motion, &other)?;
+                let (point, normal) = moving
+                    .translated((motion.0 * toi, motion.1 * toi))
+                    .contact(&other, motion);
+                Some(CastHit {
+                    entity: e,
+                    point,
+                    normal,
+                    dist: toi * max_dist,
+                })
+            })
+            .min_by(|hit1, hit2| hit1.dist.total_cmp(&hit2.dist))
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::{physics::*, prelude::*};
+
+    #[test]
+    fn test_casts() {
+        let mut world = SystemManager::from(State::default());
+        let mut entities = vec![];
+        world.update_with(|_, cmds| {
+            entities = vec![
+                cmds.create_from((
+                    Transform::at(100., 0.),
+                    Hitbox(
+                        HitboxType::Static,
+                        Shape::Rect { w: 20., h: 100. },
+                        CollisionFilter::default(),
+                    ),
+                )),
+                cmds.create_from((
+                    Transform::at(200., 0.),
+                    Hitbox(
+                        HitboxType::Dynamic,
+                        Shape::Circle { r: 10. },
+                        CollisionFilter::default(),
+                    ),
+                )),
+            ];
+        });
+        let [wall, ball] = entities[..] else {
+            unreachable!()
+        };
+        let insights = StateInsights::of(world.get_state());
+        let hit = insights
+            .raycast((0., 0.), (1., 0.), 500., |_, _| true)
+            .unwrap();
+        assert_eq!(
+            (hit.entity, hit.point, hit.normal, hit.dist),
+            (wall, (90., 0.), (-1., 0.), 90.)
+        );
+        let hit = insights
+            .raycast((0., 0.), (1., 0.), 500., |e, _| *e != wall)
+            .unwrap();
+        assert_eq!(
+            (hit.entity, hit.point, hit.normal, hit.dist),
+            (ball, (190., 0.), (-1., 0.), 190.)
+        );
+        assert!(insights
+            .raycast((0., 0.), (1., 0.), 50., |_, _| true)
+            .is_none());
+        assert!(insights
+            .raycast((0., 0.), (0., 1.), 500., |_, _| true)
+            .is_none());
+        // A box moving along the wall hits the ball.
+        let hit = insights
+            .shape_cast(
+                &Shape::Rect { w: 10., h: 10. },
+                &Transform::at(200., -100.),
+                (0., 1.),
+                500.,
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.entity, ball);
+        assert!((hit.dist - 85.).abs() < 0.01);
+        assert!((hit.point.1 + 10.).abs() < 0.01 && (hit.normal.1 + 1.).abs() < 0.01);
+        // The casts follow the hitboxes as they move.
+        world.update_with(|_, cmds| {
+            cmds.update_component(&wall, |trans: &mut Transform| trans.y = 1000.);
+        });
+        let insights = StateInsights::of(world.get_state());
+        let hit = insights
+            .raycast((0., 0.), (1., 0.), 500., |_, _| true)
+            .unwrap();
+        assert_eq!(hit.entity, ball);
+        assert!(insights
+            .raycast((0., 1000.), (1., 0.), 500., |_, _| true)
+            .is_some_and(|hit| hit.entity == wall));
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 use crate::prelude::*;
 
@@ -7,7 +7,7 @@ use super::Hitbox;
 /// Represents insights about an entity that could possibly be a collider (i.e., have a hitbox).
 pub trait ColliderInsights<'a> {
     /// Returns the entities that collide with this entity.
-    fn contacts_of(&self, e: &EntityRef) -> Option<&'a HashSet<EntityRef>>;
+    fn contacts_of(&self, e: &EntityRef) -> Option<&'a BTreeSet<EntityRef>>;
     /// Returns the concrete entities that collide with this entity.
     fn concrete_contacts_of(&self, e: &EntityRef) -> HashSet<&'a EntityRef>;
     /// Returns the set of entities that just started colliding with this entity in the last update.
@@ -17,7 +17,7 @@ pub trait ColliderInsights<'a> {
 }
 
 impl<'a, R: StateReader> ColliderInsights<'a> for StateInsights<'a, R> {
-    fn contacts_of(&self, e: &EntityRef) -> Option<&'a HashSet<EntityRef>> {
+    fn contacts_of(&self, e: &EntityRef) -> Option<&'a BTreeSet<EntityRef>> {
         self.0
             .select_one::<(InteractTarget<Hitbox>,)>(e)
             .map(|(coll_state,)| &coll_state.actors)
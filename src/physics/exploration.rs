@@ -0,0 +1,98 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{camera::*, character::Player, item::Item, prelude::*, sprite::Sprite};
+
+use super::VisionInsights;
+
+/// A resource keeping the stationary entities seen by the player, which stay drawn when they are out of sight.
+/// Ordered, so that the saved worlds are the same for the same state.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Explored(pub BTreeSet<EntityRef>);
+
+/// A system that adds the stationary entities seen by the player to the [`Explored`] ones, and forgets the removed ones.
+#[derive(Clone, Copy, Debug)]
+pub struct ExplorationSystem;
+
+impl<R: StateReader> System<R> for ExplorationSystem {
+    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+        let (Some(player), Some(((x1, y1), (x2, y2))), Some(view_dist)) = (
+            state.read_resource::<Player>(),
+            ActiveCamera::bounds(state),
+            ActiveCamera::view_distance(state),
+        ) else {
+            return;
+        };
+        let Some(visibility) = StateInsights::of(state).visibility_polygon_of(&player.0, view_dist)
+        else {
+            return;
+        };
+        let explored = state.read_resource::<Explored>();
+        let is_explored = |e: &EntityRef| explored.map(|ex| ex.0.contains(e)).unwrap_or(false);
+        let newly_explored = state
+            .select::<(Transform, Sprite, Without<Velocity>, Without<Item>)>()
+            .filter(|(e, (trans, _, _, _))| {
+                (x1..=x2).contains(&trans.x) && (y1..=y2).contains(&trans.y) && !is_explored(e)
+            })
+            .filter(|(e, (trans, _, _, _))| visibility.reveals(e, trans, state))
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>();
+        let has_removed = explored
+            .map(|ex| ex.0.iter().any(|e| !state.is_valid(e)))
+            .unwrap_or(false);
+        if newly_explored.is_empty() && !has_removed {
+            return;
+        }
+        let mut explored = explored.cloned().unwrap_or_default();
+        explored.0.retain(|e| state.is_valid(e));
+        explored.0.extend(newly_explored);
+        cmds.set_resource(explored);
+    }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_exploration() {
+        let mut world = SystemManager::from(State::default());
+        world.register_system(ExplorationSystem);
+        let mut entities = vec![];
+        world.update_with(|_, cmds| {
+            let player = cmds.create_from((Transform::at(0., 0.), CameraFollow::new(400., 400.)));
+            cmds.set_resource(Player(player));
+            cmds.set_resource(ActiveCamera(player));
+            entities = vec![
+                cmds.create_from((Transform::at(100., 0.), Sprite::new("crate", 0))),
+                cmds.create_from((Transform::at(-100., 0.), Sprite::new("crate", 0))),
+                cmds.create_from((
+                    Transform::at(0., 100.),
+                    Sprite::new("bullet", 0),
+                    Velocity::default(),
+                )),
+            ];
+        });
+        world.update_with_systems(Default::default());
+        let explored = |world: &SystemManager<State>| {
+            world
+                .get_state()
+                .read_resource::<Explored>()
+                .cloned()
+                .unwrap_or_default()
+                .0
+        };
+        // Only the stationary entities are kept.
+        assert_eq!(explored(&world), BTreeSet::from([entities[0], entities[1]]));
+        // The removed entities are forgotten.
+        world.update_with(|_, cmds| cmds.mark_for_removal(&entities[0]));
+        world.update_with_systems(Default::default());
+        world.update_with_systems(Default::default());
+        assert_eq!(explored(&world), BTreeSet::from([entities[1]]));
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+use super::HearingInsights;
+
+/// An event denoting a sound made at a position, e.g. a gunshot or a footstep.
+#[derive(Clone, Copy, Debug)]
+pub struct SoundEmittedEvt {
+    pub source: EntityRef,
+    pub pos: (f32, f32),
+    /// The distance that the sound carries in the open.
+    pub loudness: f32,
+}
+
+/// An event denoting a [`Hearing`] entity hearing a sound.
+#[derive(Clone, Copy, Debug)]
+pub struct SoundHeardEvt {
+    pub hearer: EntityRef,
+    pub source: EntityRef,
+    pub pos: (f32, f32),
+    /// The loudness left at the position of the hearer.
+    pub loudness: f32,
+}
+
+/// Entities tagged with this component hear the sounds made within the given range.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Hearing(pub f32);
+
+/// Entities tagged with this component periodically make sounds while they are moving, louder the faster they move.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MovementNoise {
+    /// The loudness of the sounds at the maximum speed.
+    pub loudness: f32,
+    /// The time in seconds between two sounds.
+    pub interval: f32,
+    remaining: f32,
+}
+
+impl MovementNoise {
+    pub fn new(loudness: f32, interval: f32) -> Self {
+        Self {
+            loudness,
+            interval,
+            remaining: interval,
+        }
+    }
+}
+
+/// A system that emits [`SoundEmittedEvt`]s for the moving entities with [`MovementNoise`].
+#[derive(Clone, Copy, Debug)]
+pub struct MovementNoiseSystem;
+
+impl<R: StateReader> System<R> for MovementNoiseSystem {
+    fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+        state
+            .select::<(
+                MovementNoise,
+                Transform,
+                Velocity,
+                Optional<MaxSpeed>,
+                Without<AnchorTransform>,
+            )>()
+            .for_each(|(e, (noise, trans, vel, max_speed, _))| {
+                let speed = (vel.x * vel.x + vel.y * vel.y).sqrt();
+                if speed < 1. {
+                    return;
+                }
+                let remaining = noise.remaining - ctx.dt;
+                if remaining > 0. {
+                    cmds.update_component(&e, move |noise: &mut MovementNoise| {
+                        noise.remaining = remaining;
+                    });
+                    return;
+                }
+                let speed_ratio = max_speed
+                    .map(|max_speed| (speed / max_speed.0).min(1.))
+                    .unwrap_or(1.);
+                cmds.emit_event(SoundEmittedEvt {
+                    source: e,
+                    pos: (trans.x, trans.y),
+                    loudness: noise.loudness * speed_ratio,
+                });
+                cmds.update_component(&e, move |noise: &mut MovementNoise| {
+                    noise.remaining = noise.interval;
+                });
+            });
+    }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
+}
+
+/// A system that lets the [`Hearing`] entities hear the emitted sounds, emitting [`SoundHeardEvt`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct HearingSystem;
+
+impl<R: StateReader> System<R> for HearingSystem {
+    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+        let insights = StateInsights::of(state);
+        state.read_events::<SoundEmittedEvt>().for_each(|evt| {
+            insights
+                .within_radius(evt.pos, evt.loudness)
+                .filter(|hearer| hearer != &evt.source)
+                .for_each(|hearer| {
+                    let Some((hearing, trans)) = state.select_one::<(Hearing, Transform)>(&hearer)
+                    else {
+                        return;
+                    };
+                    let (dx, dy) = (trans.x - evt.pos.0, trans.y - evt.pos.1);
+                    if dx * dx + dy * dy > hearing.0 * hearing.0 {
+                        return;
+                    }
+                    let loudness = insights.loudness_at(evt.pos, evt.loudness, (trans.x, trans.y));
+                    if loudness > 0. {
+                        cmds.emit_event(SoundHeardEvt {
+                            hearer,
+                            source: evt.source,
+                            pos: evt.pos,
+                            loudness,
+                        });
+                    }
+                });
+        });
+    }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
+}
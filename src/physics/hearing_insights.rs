@@ -0,0 +1,83 @@
+use crate::prelude::*;
+
+use super::{HitboxType, SoundHeardEvt};
+
+/// The ratio of the loudness that passes through a wall.
+const WALL_ATTENUATION: f32 = 0.5;
+
+pub trait HearingInsights<'a> {
+    /// Returns the loudness left at the position `pos` of a sound made at the `origin`.
+    /// The loudness drops with the distance, and is further attenuated by each static hitbox in between.
+    fn loudness_at(&self, origin: (f32, f32), loudness: f32, pos: (f32, f32)) -> f32;
+    /// Returns the sounds heard by the given entity in the last update, the loudest first.
+    fn heard_sounds_of(&self, hearer: &EntityRef) -> Vec<&'a SoundHeardEvt>;
+}
+
+impl<'a, R: StateReader> HearingInsights<'a> for StateInsights<'a, R> {
+    fn loudness_at(&self, origin: (f32, f32), loudness: f32, pos: (f32, f32)) -> f32 {
+        let (dx, dy) = (pos.0 - origin.0, pos.1 - origin.1);
+        let dist = (dx * dx + dy * dy).sqrt();
+        // Count the walls in between, i.e., the static hitboxes crossed by the line to the position.
+        let walls = notan::math::vec2(dx, dy)
+            .try_normalize()
+            .map(|dir| {
+                let bounds = (
+                    (origin.0.min(pos.0), origin.1.min(pos.1)),
+                    (origin.0.max(pos.0), origin.1.max(pos.1)),
+                );
+                self.hitboxes_within(bounds, |_, hitbox| hitbox.0 == HitboxType::Static)
+                    .filter(|(_, shape)| shape.raycast(origin, (dir.x, dir.y), dist).is_some())
+                    .count()
+            })
+            .unwrap_or(0);
+        (loudness * WALL_ATTENUATION.powi(walls as i32) - dist).max(0.)
+    }
+
+    fn heard_sounds_of(&self, hearer: &EntityRef) -> Vec<&'a SoundHeardEvt> {
+        let mut sounds: Vec<_> = self
+            .0
+            .read_events::<SoundHeardEvt>()
+            .filter(|evt| &evt.hearer == hearer)
+            .collect();
+        sounds.sort_by(|s1, s2| s2.loudness.total_cmp(&s1.loudness));
+        sounds
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::physics::*;
+
+    #[test]
+    fn test_loudness_at() {
+        let mut world = SystemManager::from(State::default());
+        world.update_with(|_, cmds| {
+            cmds.create_from((
+                Transform::at(100., 0.),
+                Hitbox(
+                    HitboxType::Static,
+                    Shape::Rect { w: 20., h: 100. },
+                    CollisionFilter::default(),
+                ),
+            ));
+            // Only the static hitboxes muffle the sounds.
+            cmds.create_from((
+                Transform::at(0., 100.),
+                Hitbox(
+                    HitboxType::Dynamic,
+                    Shape::Circle { r: 10. },
+                    CollisionFilter::default(),
+                ),
+            ));
+        });
+        let insights = StateInsights::of(world.get_state());
+        // In the open.
+        assert_eq!(insights.loudness_at((0., 0.), 500., (0., 200.)), 300.);
+        // Behind the wall.
+        assert_eq!(insights.loudness_at((0., 0.), 500., (200., 0.)), 50.);
+        // Too far to be heard.
+        assert_eq!(insights.loudness_at((0., 0.), 300., (200., 0.)), 0.);
+    }
+}
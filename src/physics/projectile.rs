@@ -1,13 +1,14 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeSet, marker::PhantomData};
 
 use crate::{
     character::CharacterInsights, item::*, needs::NeedMutator, physics::*, sprite::Sprite,
 };
 
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 /// Defines a projectile to be generated by [`ProjectileGenerator`]s.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProjectileDefn {
     pub lifetime: f32,
     pub speed: f32,
@@ -16,11 +17,17 @@ pub struct ProjectileDefn {
 }
 
 /// Entities tagged with this components will be able to generate projectiles upon interaction.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProjectileGenerator {
     pub proj: ProjectileDefn,
     pub cooldown: Option<f32>,
     pub auto_knockback: Option<f32>,
+    /// How far the shots can be heard.
+    #[serde(default)]
+    pub loudness: f32,
+    /// The calibre of the rounds used up by each shot. Fires without ammo if `None`.
+    #[serde(default)]
+    pub calibre: Option<Calibre>,
 }
 
 /// [`ProjectileGenerator`]s denote an interaction, which lets them shoot a projectile.
@@ -43,7 +50,9 @@ impl Interaction for ProjectileGenerator {
         state: &impl StateReader,
     ) -> bool {
         let insights = StateInsights::of(state);
-        insights.is_equipping(actor, target) && insights.is_character(actor)
+        insights.is_equipping(actor, target)
+            && insights.is_character(actor)
+            && !insights.is_reloading(target)
     }
 
     fn can_end_untargeted(
@@ -56,7 +65,7 @@ impl Interaction for ProjectileGenerator {
 }
 
 /// A request to generate a projectile from the wrapped entity.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct GenerateProjectileReq {
     actor_entity: EntityRef,
     gen_entity: EntityRef,
@@ -104,10 +113,24 @@ impl<R: StateReader> System<R> for ProjectileGenerationSystem {
                     if interact_target.actors.len() == 0 {
                         return;
                     }
+                    // Use up a round, if the generator needs ammo.
+                    let insights = StateInsights::of(state);
+                    if insights.calibre_of(&gen_entity).is_some() {
+                        if let Some(round) = insights.next_round_for(&actor_entity, &gen_entity) {
+                            cmds.mark_for_removal(&round);
+                        } else {
+                            // Out of ammo, stop firing and try to reload.
+                            cmds.emit_event(UninteractReq::<ProjectileGenerator>::new(
+                                actor_entity,
+                                gen_entity,
+                            ));
+                            cmds.emit_event(InteractReq::<Magazine>::new(actor_entity, gen_entity));
+                            return;
+                        }
+                    }
                     // Compute the new velocity of the projectile.
                     let rand_spread = if p_gen.proj.spread > 0. {
-                        rand::thread_rng().gen_range(0.0..p_gen.proj.spread)
-                            - p_gen.proj.spread / 2.
+                        cmds.rng().gen_range(0.0..p_gen.proj.spread) - p_gen.proj.spread / 2.
                     } else {
                         0.
                     };
@@ -132,14 +155,30 @@ impl<R: StateReader> System<R> for ProjectileGenerationSystem {
                         Lifetime {
                             remaining_time: p_gen.proj.lifetime,
                         },
-                        Hitbox(HitboxType::Ghost, Shape::Circle { r: 5. }),
+                        Hitbox(
+                            HitboxType::Ghost,
+                            Shape::Circle { r: 5. },
+                            CollisionFilter::new(
+                                Layers::PROJECTILES,
+                                Layers::WALLS | Layers::CHARACTERS | Layers::VEHICLES,
+                            ),
+                        ),
                         InteractTarget::<Hitbox>::default(),
                         // Do not hit the anchor parent.
                         Hitter::new(friendly_entities),
+                        FastMoving,
                         SuicideOnHit,
                         ApplyOnHit::new(Some(0.), p_gen.proj.on_hit.clone()),
                         Sprite::new("bullet", 2),
                     ));
+                    // Let the shot be heard.
+                    if p_gen.loudness > 0. {
+                        cmds.emit_event(SoundEmittedEvt {
+                            source: actor_entity,
+                            pos: (trans.x, trans.y),
+                            loudness: p_gen.loudness,
+                        });
+                    }
                     // Apply knockback optionally
                     if let Some(knockback_factor) = p_gen.auto_knockback {
                         let knockback_vel = dir * -1. * knockback_factor;
@@ -167,10 +206,10 @@ impl<R: StateReader> System<R> for ProjectileGenerationSystem {
 }
 
 /// Represents an entity that can impact other entities on collision.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Hitter {
     /// Hitting these entities will do nothing.
-    friendly_entities: HashSet<EntityRef>,
+    friendly_entities: BTreeSet<EntityRef>,
 }
 
 impl EntityRefBag for Hitter {
@@ -190,29 +229,110 @@ impl EntityRefBag for Hitter {
 impl Hitter {
     pub fn new(exceptions: impl IntoIterator<Item = EntityRef>) -> Self {
         Self {
-            friendly_entities: HashSet::from_iter(exceptions),
+            friendly_entities: BTreeSet::from_iter(exceptions),
         }
     }
 }
 
+/// Marks a [`Hitter`] that moves too fast for the discrete collision checks, such as a bullet.
+/// Its hitbox is swept along its path instead, and it stops at the first concrete entity it hits.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FastMoving;
+
 /// An event denoting a [`Hitter`] hitting a concrete entity.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct HitEvt {
     pub hitter: EntityRef,
     pub target: EntityRef,
     pub hit_velocity: (f32, f32),
+    /// The point of the hitter that touched the target.
+    pub contact_point: (f32, f32),
 }
 
 /// A system that listens to [`Hitter`] collision and emits appropriate [`HitEvt`]s.
 #[derive(Clone, Copy, Debug)]
 pub struct HitSystem;
 
+impl HitSystem {
+    /// Sweeps the hitboxes of the [`FastMoving`] hitters along their motion in this update.
+    /// Each of them hits the first concrete entity along its path, and stops there.
+    fn sweep_fast_hitters(ctx: &UpdateContext, state: &impl StateReader, cmds: &mut StateCommands) {
+        let sweeps = state
+            .select::<(Hitter, Velocity, Transform, With<FastMoving>)>()
+            .filter(|(_, (_, vel, _, _))| vel.x != 0. || vel.y != 0.)
+            .flat_map(|(e, (hitter, vel, trans, _))| {
+                let ehb = EffectiveHitbox::new(&e, state)?;
+                Some((ehb, hitter, *vel, trans))
+            })
+            .collect_vec();
+        if sweeps.is_empty() {
+            return;
+        }
+        let targets = state
+            .select::<(Hitbox, With<Transform>)>()
+            .filter(|(_, (hb, _))| hb.0.is_concrete())
+            .flat_map(|(e, _)| EffectiveHitbox::new(&e, state))
+            .collect_vec();
+        let motion_of = |vel: &Velocity| (vel.x * ctx.dt, vel.y * ctx.dt);
+        let aabbs = sweeps
+            .iter()
+            .map(|(ehb, _, vel, _)| ehb.shape.swept(motion_of(vel)).aabb())
+            .chain(targets.iter().map(|ehb| ehb.shape.aabb()));
+        // The earliest impact along each sweep, along with the target.
+        let mut impacts: Vec<Option<(f32, &EffectiveHitbox)>> = vec![None; sweeps.len()];
+        for (i, j) in overlapping_pairs(aabbs) {
+            // Only consider the pairs of a sweep & a target.
+            if i >= sweeps.len() || j < sweeps.len() {
+                continue;
+            }
+            let (ehb, hitter, vel, _) = &sweeps[i];
+            let target = &targets[j - sweeps.len()];
+            if target.entity == ehb.entity
+                || hitter.friendly_entities.contains(&target.entity)
+                || !ehb.hitbox.2.matches(&target.hitbox.2)
+            {
+                continue;
+            }
+            let Some(toi) = ehb.shape.time_of_impact(motion_of(vel), &target.shape) else {
+                continue;
+            };
+            if impacts[i].map(|(min_toi, _)| toi < min_toi).unwrap_or(true) {
+                impacts[i] = Some((toi, target));
+            }
+        }
+        sweeps
+            .iter()
+            .zip(impacts)
+            .for_each(|((ehb, _, vel, trans), impact)| {
+                let Some((toi, target)) = impact else {
+                    return;
+                };
+                let (dx, dy) = motion_of(vel);
+                let shape = ehb.shape.translated((dx * toi, dy * toi));
+                let (contact_point, _) = shape.contact(&target.shape, (vel.x, vel.y));
+                cmds.emit_event(HitEvt {
+                    hitter: ehb.entity,
+                    target: target.entity,
+                    hit_velocity: (vel.x, vel.y),
+                    contact_point,
+                });
+                // Stop at the impact, overriding the movement of this update.
+                let (x, y) = (trans.x + dx * toi, trans.y + dy * toi);
+                cmds.update_component(&ehb.entity, move |trans: &mut Transform| {
+                    trans.x = x;
+                    trans.y = y;
+                });
+                cmds.set_component(&ehb.entity, Velocity::default());
+            });
+    }
+}
+
 impl<R: StateReader> System<R> for HitSystem {
-    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+    fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         // Emit the projectile hit events.
         state
-            .select::<(Hitter, Velocity)>()
-            .for_each(|(hitter_entity, (hitter, hitter_vel))| {
+            .select::<(Hitter, Velocity, Without<FastMoving>)>()
+            .for_each(|(hitter_entity, (hitter, hitter_vel, _))| {
                 StateInsights::of(state)
                     .new_collision_starters_of(&hitter_entity)
                     .into_iter()
@@ -226,13 +346,20 @@ impl<R: StateReader> System<R> for HitSystem {
                             .unwrap_or(false)
                     })
                     .for_each(|coll_target| {
+                        let hit_velocity = (hitter_vel.x, hitter_vel.y);
+                        let (contact_point, _) = EffectiveHitbox::new(&hitter_entity, state)
+                            .zip(EffectiveHitbox::new(coll_target, state))
+                            .map(|(ehb, target)| ehb.shape.contact(&target.shape, hit_velocity))
+                            .unwrap_or_default();
                         cmds.emit_event(HitEvt {
                             hitter: hitter_entity,
                             target: *coll_target,
-                            hit_velocity: (hitter_vel.x, hitter_vel.y),
+                            hit_velocity,
+                            contact_point,
                         })
                     });
             });
+        Self::sweep_fast_hitters(ctx, state, cmds);
         state.select::<(Hitter,)>().for_each(|(e, _)| {
             cmds.remove_invalids::<Hitter>(&e);
         })
@@ -240,7 +367,7 @@ impl<R: StateReader> System<R> for HitSystem {
 }
 
 /// Entities tagged with this component will be removed after they hit another component.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct SuicideOnHit;
 
 /// A system that removes the [`SuicideOnHit`] entities from the system when they hit a concrete entity.
@@ -259,7 +386,7 @@ impl<R: StateReader> System<R> for SuicideOnHitSystem {
 }
 
 /// The entities that are hit by this entity will be applied this component with an optional time.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ApplyOnHit<T: Component> {
     time: Option<f32>,
     component: T,
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+/// The physical properties of a hitbox that determine how it responds to collisions.
+/// Dynamic hitboxes without a rigid body respond like [`RigidBody::default`], and static hitboxes have an infinite mass.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RigidBody {
+    pub mass: f32,
+    /// How much of the approaching velocity is bounced back, from 0 (no bounce) to 1 (perfectly elastic).
+    pub restitution: f32,
+    /// The ratio of the sliding resistance to the impact, from 0 (frictionless).
+    pub friction: f32,
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self::new(1., 0., 0.)
+    }
+}
+
+impl RigidBody {
+    pub fn new(mass: f32, restitution: f32, friction: f32) -> Self {
+        Self {
+            mass,
+            restitution,
+            friction,
+        }
+    }
+}
+
+/// A body taking part in a collision.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct ContactBody {
+    /// Zero for the bodies that can't be moved.
+    pub inv_mass: f32,
+    pub vel: (f32, f32),
+    pub body: RigidBody,
+}
+
+/// Returns the changes in the velocities of the two colliding bodies, given the contact normal pointing from the first to the second.
+/// The bounciest restitution & the average friction of the bodies are used.
+pub(super) fn collision_impulse(
+    b1: &ContactBody,
+    b2: &ContactBody,
+    normal: (f32, f32),
+) -> ((f32, f32), (f32, f32)) {
+    let inv_mass_sum = b1.inv_mass + b2.inv_mass;
+    let normal = notan::math::vec2(normal.0, normal.1);
+    let rel_vel = notan::math::vec2(b2.vel.0 - b1.vel.0, b2.vel.1 - b1.vel.1);
+    let normal_speed = rel_vel.dot(normal);
+    // Separating already.
+    if inv_mass_sum <= 0. || normal_speed >= 0. {
+        return ((0., 0.), (0., 0.));
+    }
+    let restitution = b1.body.restitution.max(b2.body.restitution);
+    let normal_impulse = -(1. + restitution) * normal_speed / inv_mass_sum;
+    // Resist the sliding, at most by the friction times the normal impulse.
+    let tangent_vel = rel_vel - normal_speed * normal;
+    let friction = (b1.body.friction + b2.body.friction) / 2.;
+    let tangent_impulse = (tangent_vel.length() / inv_mass_sum).min(friction * normal_impulse);
+    let impulse = normal_impulse * normal - tangent_impulse * tangent_vel.normalize_or_zero();
+    let (dvel1, dvel2) = (-impulse * b1.inv_mass, impulse * b2.inv_mass);
+    ((dvel1.x, dvel1.y), (dvel2.x, dvel2.y))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_collision_impulse() {
+        let close = |(x1, y1): (f32, f32), (x2, y2): (f32, f32)| {
+            (x1 - x2).abs() < 1e-2 && (y1 - y2).abs() < 1e-2
+        };
+        // A car runs into a standing character, and they move together.
+        let car = ContactBody {
+            inv_mass: 1. / 1000.,
+            vel: (1000., 0.),
+            body: RigidBody::new(1000., 0., 0.),
+        };
+        let character = ContactBody {
+            inv_mass: 1. / 70.,
+            vel: (0., 0.),
+            body: RigidBody::new(70., 0., 0.),
+        };
+        let (dvel1, dvel2) = collision_impulse(&car, &character, (1., 0.));
+        let shared_vel = 1000. * 1000. / 1070.;
+        assert!(close((1000. + dvel1.0, dvel1.1), (shared_vel, 0.)));
+        assert!(close(dvel2, (shared_vel, 0.)));
+        // A crate bounces off a wall, and the friction slows down its sliding.
+        let wall = ContactBody {
+            inv_mass: 0.,
+            vel: (0., 0.),
+            body: RigidBody::default(),
+        };
+        let crate_body = ContactBody {
+            inv_mass: 1. / 50.,
+            vel: (100., 100.),
+            body: RigidBody::new(50., 0.5, 0.2),
+        };
+        let (dvel1, dvel2) = collision_impulse(&crate_body, &wall, (1., 0.));
+        assert!(close(dvel1, (-150., -15.)));
+        assert_eq!(dvel2, (0., 0.));
+        // Moving away from the wall.
+        let (dvel1, _) = collision_impulse(&crate_body, &wall, (-1., 0.));
+        assert_eq!(dvel1, (0., 0.));
+    }
+}
@@ -0,0 +1,174 @@
+use std::f32::consts::TAU;
+
+use itertools::Itertools;
+
+use crate::prelude::*;
+
+use super::{EffectiveHitbox, Shape, TransformedShape};
+
+/// The number of rays cast evenly around the origin, which bound the polygon by the maximum distance in the open.
+const OPEN_RAYS: usize = 64;
+/// The angle in radians by which the rays pass the edges of the occluders, to reach what lies behind them.
+const EDGE_ANGLE: f32 = 1e-4;
+/// The distance by which the polygon is grown when checking for overlaps, so that it covers the occluders it touches.
+const OVERLAP_MARGIN: f32 = 1.;
+
+/// The region visible from an origin, which is bounded by the occluders & the maximum distance.
+#[derive(Clone, Debug)]
+pub struct VisibilityPolygon {
+    pub origin: (f32, f32),
+    /// The vertices of the polygon, sorted by their angle around the origin.
+    pub vertices: Vec<(f32, f32)>,
+}
+
+impl VisibilityPolygon {
+    /// Computes the visibility polygon by casting rays from the origin towards the edges of the occluders.
+    /// The occluders containing the origin should be left out, as they would block everything.
+    pub fn compute(origin: (f32, f32), max_dist: f32, occluders: &[TransformedShape]) -> Self {
+        let parts = occluders
+            .iter()
+            .flat_map(TransformedShape::parts)
+            .collect_vec();
+        let edge_angles = parts
+            .iter()
+            .flat_map(|part| silhouette_angles(origin, part));
+        let vertices = (0..OPEN_RAYS)
+            .map(|i| i as f32 * TAU / OPEN_RAYS as f32)
+            .chain(edge_angles.flat_map(|angle| [angle - EDGE_ANGLE, angle, angle + EDGE_ANGLE]))
+            .map(|angle| angle.rem_euclid(TAU))
+            .sorted_by(f32::total_cmp)
+            .dedup()
+            .map(|angle| {
+                let dir = (angle.cos(), angle.sin());
+                let dist = parts
+                    .iter()
+                    .flat_map(|part| part.raycast(origin, dir, max_dist))
+                    .map(|(dist, _)| dist)
+                    .fold(max_dist, f32::min);
+                (origin.0 + dir.0 * dist, origin.1 + dir.1 * dist)
+            })
+            .collect();
+        Self { origin, vertices }
+    }
+
+    /// Returns the top-left & bottom-right corners of the bounding box of the polygon.
+    pub fn aabb(&self) -> ((f32, f32), (f32, f32)) {
+        self.vertices.iter().fold(
+            (self.origin, self.origin),
+            |((x1, y1), (x2, y2)), &(x, y)| ((x1.min(x), y1.min(y)), (x2.max(x), y2.max(y))),
+        )
+    }
+
+    /// Returns true if the point is within the polygon.
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        // Count the edges crossed by a ray towards the right.
+        self.vertices
+            .iter()
+            .circular_tuple_windows()
+            .filter(|((x1, y1), (x2, y2))| {
+                (*y1 > y) != (*y2 > y) && x < x1 + (y - y1) * (x2 - x1) / (y2 - y1)
+            })
+            .count()
+            % 2
+            == 1
+    }
+
+    /// Returns true if the shape is at least partly within the polygon, including the occluders that bound it.
+    pub fn overlaps(&self, shape: &TransformedShape) -> bool {
+        let ((sx1, sy1), (sx2, sy2)) = shape.aabb();
+        let grown = |(x, y): (f32, f32)| {
+            let dir = notan::math::vec2(x - self.origin.0, y - self.origin.1).normalize_or_zero();
+            (x + dir.x * OVERLAP_MARGIN, y + dir.y * OVERLAP_MARGIN)
+        };
+        // Check the triangles between the origin & the edges of the polygon.
+        self.vertices
+            .iter()
+            .map(|&v| grown(v))
+            .circular_tuple_windows()
+            .any(|(v1, v2)| {
+                let o = self.origin;
+                let area = (v1.0 - o.0) * (v2.1 - o.1) - (v1.1 - o.1) * (v2.0 - o.0);
+                let (tx1, tx2) = (o.0.min(v1.0).min(v2.0), o.0.max(v1.0).max(v2.0));
+                let (ty1, ty2) = (o.1.min(v1.1).min(v2.1), o.1.max(v1.1).max(v2.1));
+                if area.abs() < 1e-3 || tx2 < sx1 || sx2 < tx1 || ty2 < sy1 || sy2 < ty1 {
+                    return false;
+                }
+                let Ok(triangle) = Shape::polygon(vec![o, v1, v2]) else {
+                    return false;
+                };
+                TransformedShape::new(&Transform::default(), &triangle).overlaps(shape)
+            })
+    }
+
+    /// Returns true if the given entity is at least partly within the polygon, judging by its hitbox or else by its position.
+    pub fn reveals(&self, e: &EntityRef, trans: &Transform, state: &impl StateReader) -> bool {
+        EffectiveHitbox::new(e, state)
+            .map(|ehb| self.overlaps(&ehb.shape))
+            .unwrap_or_else(|| self.contains((trans.x, trans.y)))
+    }
+}
+
+/// Returns the angles around the origin at which the rays graze the edges of the primitive shape.
+fn silhouette_angles(origin: (f32, f32), shape: &TransformedShape) -> Vec<f32> {
+    let angle_to = |(x, y): (f32, f32)| (y - origin.1).atan2(x - origin.0);
+    // The tangents of a circle from the origin.
+    let tangents = |center: (f32, f32), r: f32| {
+        let dist = ((center.0 - origin.0).powi(2) + (center.1 - origin.1).powi(2)).sqrt();
+        if dist <= r {
+            return vec![];
+        }
+        let half_width = (r / dist).asin();
+        let angle = angle_to(center);
+        vec![angle - half_width, angle + half_width]
+    };
+    match shape {
+        TransformedShape::Circle(circle) => tangents(circle.position, circle.radius),
+        TransformedShape::Poly(poly) => poly
+            .vertices
+            .iter()
+            .map(|(x, y)| angle_to((poly.position.0 + x, poly.position.1 + y)))
+            .collect(),
+        TransformedShape::Capsule(capsule) => {
+            let (x, y) = capsule.position;
+            let (ax, ay) = capsule.arm();
+            [(x + ax, y + ay), (x - ax, y - ay)]
+                .into_iter()
+                .flat_map(|end| tangents(end, capsule.radius))
+                .collect()
+        }
+        TransformedShape::Compound(parts) => parts
+            .iter()
+            .flat_map(|part| silhouette_angles(origin, part))
+            .collect(),
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_visibility_polygon() {
+        // A wall to the right, and a pillar below.
+        let wall =
+            TransformedShape::new(&Transform::at(100., 0.), &Shape::Rect { w: 20., h: 100. });
+        let pillar = TransformedShape::new(&Transform::at(0., 100.), &Shape::Circle { r: 20. });
+        let poly = VisibilityPolygon::compute((0., 0.), 300., &[wall.clone(), pillar.clone()]);
+        // The open space is seen up to the maximum distance.
+        assert!(poly.contains((-250., 0.)) && poly.contains((0., -250.)));
+        assert!(!poly.contains((-350., 0.)));
+        // Behind the occluders is hidden, but their sides are seen.
+        assert!(poly.contains((80., 0.)) && !poly.contains((150., 0.)));
+        assert!(poly.contains((150., -100.)));
+        assert!(poly.contains((0., 70.)) && !poly.contains((0., 150.)));
+        assert!(poly.contains((50., 150.)));
+        // The silhouette of the wall is followed closely.
+        assert!(poly.contains((150., -90.)) && !poly.contains((150., -75.)));
+        // The occluders themselves are seen, unlike the things behind them.
+        assert!(poly.overlaps(&wall) && poly.overlaps(&pillar));
+        let hidden = TransformedShape::new(&Transform::at(200., 0.), &Shape::Circle { r: 10. });
+        assert!(!poly.overlaps(&hidden));
+        let peeking = TransformedShape::new(&Transform::at(200., -130.), &Shape::Circle { r: 10. });
+        assert!(poly.overlaps(&peeking));
+    }
+}
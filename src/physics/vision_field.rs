@@ -1,14 +1,74 @@
 use std::collections::HashSet;
 
-use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
-use super::{ColliderInsights, EffectiveHitbox, Hitbox};
+use super::{CastInsights, ColliderInsights, VisionInsights};
 
 /// Entities tagged with this component will initiate interactions with the entities that collide and are visible from the position of this entity.
-#[derive(Clone, Copy, Debug)]
-pub struct VisionField(pub f32);
+/// The field is a cone around the facing direction of the anchor parent (or the entity itself, if it has no anchor parent).
+/// The targets in the cone are noticed right away within the near range. Further away, or closer to the edges of the cone, noticing takes longer.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct VisionField {
+    /// The angle of the cone in degrees.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    /// The time in seconds it takes to notice a target at the far edge of the cone.
+    pub notice_time: f32,
+}
+
+impl VisionField {
+    pub fn new(fov: f32, near: f32, far: f32, notice_time: f32) -> Self {
+        Self {
+            fov,
+            near,
+            far,
+            notice_time,
+        }
+    }
+
+    /// Returns the time it takes to notice a target at the given offset from the viewer facing the given direction (in degrees).
+    /// Returns `None` if the target is out of the field.
+    pub fn notice_time_at(&self, facing_deg: f32, offset: (f32, f32)) -> Option<f32> {
+        let (y, x) = facing_deg.to_radians().sin_cos();
+        let facing = notan::math::vec2(x, -y);
+        let offset = notan::math::vec2(offset.0, offset.1);
+        let dist = offset.length();
+        let angle = if dist > 0. {
+            facing.angle_between(offset).abs().to_degrees()
+        } else {
+            0.
+        };
+        if dist > self.far || angle > self.fov / 2. {
+            return None;
+        }
+        if dist <= self.near {
+            return Some(0.);
+        }
+        // The peripheral falloff, by the distance or the angle, whichever is further out.
+        let dist_falloff = (dist - self.near) / (self.far - self.near);
+        let angle_falloff = angle / (self.fov / 2.);
+        Some(self.notice_time * dist_falloff.max(angle_falloff))
+    }
+}
+
+/// The progress of a vision field in noticing the targets in its field, from 0 to 1.
+/// Only the targets that are in the field and are not noticed yet are kept.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VisionAwareness(pub Vec<(EntityRef, f32)>);
+
+impl VisionAwareness {
+    /// Returns the progress of noticing the given target.
+    pub fn progress_of(&self, target: &EntityRef) -> f32 {
+        self.0
+            .iter()
+            .find(|(e, _)| e == target)
+            .map(|(_, progress)| *progress)
+            .unwrap_or(0.)
+    }
+}
 
 impl Interaction for VisionField {
     fn priority() -> usize {
@@ -47,7 +107,7 @@ impl Interaction for VisionField {
 pub struct VisionSystem;
 
 impl<R: StateReader> System<R> for VisionSystem {
-    fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+    fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         state.select::<(VisionField,)>().for_each(|(e, _)| {
             StateInsights::of(state)
                 .new_collision_enders_of(&e)
@@ -57,8 +117,8 @@ impl<R: StateReader> System<R> for VisionSystem {
                 });
         });
         state
-            .select::<(VisionField, Transform)>()
-            .for_each(|(vf_entity, (_, vf_trans))| {
+            .select::<(VisionField, Transform, Optional<VisionAwareness>)>()
+            .for_each(|(vf_entity, (vf, vf_trans, awareness))| {
                 let insights = StateInsights::of(state);
                 let ref_trans = insights
                     .anchor_parent_of(&vf_entity)
@@ -86,58 +146,96 @@ impl<R: StateReader> System<R> for VisionSystem {
                             .collect()
                     })
                     .unwrap_or_default();
-                let colliding_hitboxes = colliding_entities
+                // Find the entities in the cone, along with the time it takes to notice them.
+                let in_field: Vec<_> = colliding_entities
                     .iter()
-                    .filter_map(|colliding_e| {
-                        state
-                            .select_one::<(Hitbox, Transform)>(colliding_e)
-                            .map(|(_, trans)| {
-                                (
-                                    *colliding_e,
-                                    trans,
-                                    EffectiveHitbox::new(colliding_e, state).unwrap(),
-                                )
-                            })
+                    .flat_map(|colliding_e| {
+                        let target_trans = insights.transform_of(colliding_e)?;
+                        let (dx, dy) = (target_trans.x - ref_trans.x, target_trans.y - ref_trans.y);
+                        let notice_time = vf.notice_time_at(ref_trans.deg, (dx, dy))?;
+                        Some((*colliding_e, (dx, dy), notice_time))
                     })
-                    .collect_vec();
-                // Draw lines from the vision field reference position to the entities it is colliding with.
-                let lines_to_colliders = colliding_hitboxes
-                    .iter()
-                    .map(|(colliding_e, trans, _)| {
-                        (*colliding_e, notan::math::vec2(trans.x, trans.y))
-                    })
-                    .collect_vec();
-                // Get the unobstructed entities.
-                let unobstructed_entities: HashSet<_> = lines_to_colliders
-                    .into_iter()
-                    .filter(|(colliding_e, target_pos)| {
+                    // Cast rays from the vision field reference position to the entities in the cone.
+                    .filter(|(colliding_e, (dx, dy), _)| {
                         // Make sure that the `target` entity is not obstructed by any other entity.
-                        colliding_hitboxes
-                            .iter()
-                            // Do not consider itself as a blocker.
-                            .filter(|(e, _, _)| e != colliding_e)
-                            // Only concrete hitboxes can block views.
-                            .filter(|(_, _, ehb)| ehb.hitbox.0.is_concrete())
-                            .all(|(_, _, ehb)| {
-                                !sepax2d::line::intersects_segment(
-                                    ehb.shape.shape_ref(),
-                                    (ref_trans.x, ref_trans.y),
-                                    (target_pos.x, target_pos.y),
-                                )
-                            })
+                        insights
+                            .raycast(
+                                (ref_trans.x, ref_trans.y),
+                                (*dx, *dy),
+                                (dx * dx + dy * dy).sqrt(),
+                                // Only concrete hitboxes other than the target & the viewer can block views.
+                                |e, hitbox| {
+                                    hitbox.0.is_concrete()
+                                        && e != colliding_e
+                                        && Some(e) != vf_anchor_parent
+                                },
+                            )
+                            .is_none()
                     })
-                    .map(|(colliding_e, _)| colliding_e)
+                    .map(|(colliding_e, _, notice_time)| (colliding_e, notice_time))
                     .collect();
-                let obstructed_entities: HashSet<_> = colliding_entities
-                    .difference(&unobstructed_entities)
+                // Notice the entities in the field gradually, unless they are already seen.
+                let visibles = insights.visibles_of(&vf_entity);
+                let mut noticing = vec![];
+                let mut seen_entities = HashSet::new();
+                in_field.into_iter().for_each(|(target, notice_time)| {
+                    let progress = if visibles.contains(&target) || notice_time <= ctx.dt {
+                        1.
+                    } else {
+                        awareness
+                            .map(|awareness| awareness.progress_of(&target))
+                            .unwrap_or(0.)
+                            + ctx.dt / notice_time
+                    };
+                    if progress >= 1. {
+                        seen_entities.insert(target);
+                    } else {
+                        noticing.push((target, progress));
+                    }
+                });
+                let was_noticing = awareness
+                    .map(|awareness| !awareness.0.is_empty())
+                    .unwrap_or(false);
+                if was_noticing || !noticing.is_empty() {
+                    cmds.set_component(&vf_entity, VisionAwareness(noticing));
+                }
+                let unseen_entities: HashSet<_> = colliding_entities
+                    .difference(&seen_entities)
                     .cloned()
                     .collect();
-                unobstructed_entities.into_iter().for_each(|vision_target| {
+                seen_entities.into_iter().for_each(|vision_target| {
                     cmds.emit_event(InteractReq::<VisionField>::new(vf_entity, vision_target))
                 });
-                obstructed_entities.into_iter().for_each(|vision_target| {
+                unseen_entities.into_iter().for_each(|vision_target| {
                     cmds.emit_event(UninteractReq::<VisionField>::new(vf_entity, vision_target))
                 })
             });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_notice_time() {
+        let vf = VisionField::new(90., 100., 300., 2.);
+        // Facing right, anything close in front of the viewer is noticed right away.
+        assert_eq!(vf.notice_time_at(0., (50., 0.)), Some(0.));
+        assert_eq!(vf.notice_time_at(0., (50., 40.)), Some(0.));
+        // Nothing behind the viewer or beyond the far range is seen.
+        assert_eq!(vf.notice_time_at(0., (-50., 0.)), None);
+        assert_eq!(vf.notice_time_at(0., (350., 0.)), None);
+        // Further away, or off to the side, takes longer.
+        assert_eq!(vf.notice_time_at(0., (200., 0.)), Some(1.));
+        let side_time = vf.notice_time_at(0., (200., 200. * 0.3)).unwrap();
+        assert!(side_time > 1. && side_time < 2.);
+        // Facing up, the y-axis being inverted.
+        assert_eq!(vf.notice_time_at(90., (0., -50.)), Some(0.));
+        assert_eq!(vf.notice_time_at(90., (0., 50.)), None);
+    }
 }
@@ -1,29 +1,53 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 use crate::prelude::*;
 
-use super::VisionField;
+use super::{Shape, TransformedShape, VisibilityPolygon, VisionField};
 
 /// Represents insights about an entity that could possibly be seen (i.e., in a vision field) or see (i.e., has a vision field).
 pub trait VisionInsights<'a> {
     /// Returns the set of vision field entities that can see the given entity.
-    fn viewers_of(&self, viewable_entity: &EntityRef) -> Option<&'a HashSet<EntityRef>>;
+    fn viewers_of(&self, viewable_entity: &EntityRef) -> Option<&'a BTreeSet<EntityRef>>;
     /// Returns the set of entities that are being seen by the given vision field entity.
     fn visibles_of(&self, vision_field_entity: &EntityRef) -> HashSet<EntityRef>;
+    /// Returns the region visible from the position of the given entity within the given distance, which is bounded by the concrete hitboxes.
+    /// The entity itself, its anchor parent and the hitboxes around its position do not block the view.
+    fn visibility_polygon_of(&self, viewer: &EntityRef, max_dist: f32)
+        -> Option<VisibilityPolygon>;
 }
 
 impl<'a, R: StateReader> VisionInsights<'a> for StateInsights<'a, R> {
-    fn viewers_of(&self, viewable_entity: &EntityRef) -> Option<&'a HashSet<EntityRef>> {
+    fn viewers_of(&self, viewable_entity: &EntityRef) -> Option<&'a BTreeSet<EntityRef>> {
         self.0
             .select_one::<(InteractTarget<VisionField>,)>(viewable_entity)
             .map(|(vf_intr,)| &vf_intr.actors)
     }
 
     fn visibles_of(&self, vision_field_entity: &EntityRef) -> HashSet<EntityRef> {
-        self.0
-            .select::<(InteractTarget<VisionField>,)>()
-            .filter(|(_, (vf_intr,))| vf_intr.actors.contains(vision_field_entity))
-            .map(|(e, _)| e)
+        self.interactions_of::<VisionField>(vision_field_entity)
             .collect()
     }
+
+    fn visibility_polygon_of(
+        &self,
+        viewer: &EntityRef,
+        max_dist: f32,
+    ) -> Option<VisibilityPolygon> {
+        let trans = self.transform_of(viewer)?;
+        let origin = (trans.x, trans.y);
+        let anchor_parent = self.anchor_parent_of(viewer);
+        let bounds = (
+            (origin.0 - max_dist, origin.1 - max_dist),
+            (origin.0 + max_dist, origin.1 + max_dist),
+        );
+        let eye = TransformedShape::new(trans, &Shape::Circle { r: 0.5 });
+        let occluders = self
+            .hitboxes_within(bounds, |e, hitbox| {
+                hitbox.0.is_concrete() && e != viewer && Some(e) != anchor_parent
+            })
+            .map(|(_, shape)| shape)
+            .filter(|shape| !shape.overlaps(&eye))
+            .collect::<Vec<_>>();
+        Some(VisibilityPolygon::compute(origin, max_dist, &occluders))
+    }
 }
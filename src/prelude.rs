@@ -1,3 +1,6 @@
+use itertools::Itertools;
+use rayon::prelude::*;
+
 mod basic_components;
 mod basic_systems;
 mod component;
@@ -7,24 +10,31 @@ mod entity_bundle;
 mod event;
 mod generic_bag;
 mod interaction;
+mod resource;
+mod spatial_index;
 mod state;
 mod state_insights;
 mod system;
 mod tags;
 mod timed;
+mod world_rng;
 
 pub use basic_components::*;
 pub use basic_systems::*;
-pub use component::Component;
+pub use component::{ChangeTick, Component, StorageKind};
+pub use component_tuple::{Optional, With, Without};
 pub use entity::*;
 pub use entity_bundle::*;
-pub use event::Event;
+pub use event::{Event, EventReader, EventSeq};
 pub use interaction::*;
-pub use state::{State, StateCommands, StateReader};
+pub use resource::Resource;
+pub use spatial_index::SpatialIndex;
+pub use state::{SnapshotRegistry, State, StateCommands, StateReader, StateSnapshot};
 pub use state_insights::*;
 pub use system::*;
 pub use tags::*;
 pub use timed::*;
+pub use world_rng::*;
 
 /// Represents the game world.
 #[derive(Debug, Default)]
@@ -32,7 +42,13 @@ pub struct SystemManager<R: StateReader> {
     /// The state of the world.
     state: R,
     /// The list of registered systems that read & update the state.
-    systems: Vec<Box<dyn System<R>>>,
+    systems: Vec<SystemEntry<R>>,
+    /// The order in which the systems are run. The last valid one is kept if the constraints of the later registrations contradict each other.
+    schedule: Vec<usize>,
+    /// Set by the registrations, so that the schedule is computed again before the next update.
+    schedule_outdated: bool,
+    /// Runs all the systems on the current thread, even the ones that can run in parallel.
+    serial: bool,
 }
 
 impl<R: StateReader> From<R> for SystemManager<R> {
@@ -41,6 +57,9 @@ impl<R: StateReader> From<R> for SystemManager<R> {
         Self {
             state,
             systems: Default::default(),
+            schedule: Default::default(),
+            schedule_outdated: false,
+            serial: false,
         }
     }
 }
@@ -52,13 +71,37 @@ impl<R: StateReader> SystemManager<R> {
     }
 
     /// Updates the state of the world, returning the old state.
+    /// The systems will see the whole new state as changed.
     pub fn set_state(&mut self, new_state: R) -> R {
+        self.systems.iter_mut().for_each(|entry| entry.last_run = 0);
         std::mem::replace(&mut self.state, new_state)
     }
 
     /// Registers a new system to this world.
-    pub fn register_system<S: System<R>>(&mut self, system: S) {
-        self.systems.push(Box::new(system))
+    /// Returns the registered entry, through which the stage & the ordering constraints of the system can be set.
+    pub fn register_system<S: System<R>>(&mut self, system: S) -> &mut SystemEntry<R> {
+        self.schedule_outdated = true;
+        self.systems.push(SystemEntry::new(system));
+        self.systems.last_mut().unwrap()
+    }
+
+    /// Computes the order of the registered systems. Fails if their constraints contradict each other, keeping the last valid order.
+    pub fn build_schedule(&mut self) -> anyhow::Result<()> {
+        self.schedule_outdated = false;
+        self.schedule = compute_schedule(&self.systems)?;
+        Ok(())
+    }
+
+    /// Returns the registered systems in the order their commands are applied.
+    pub fn scheduled_systems(&mut self) -> anyhow::Result<Vec<&SystemEntry<R>>> {
+        self.build_schedule()?;
+        Ok(self.schedule.iter().map(|&i| &self.systems[i]).collect())
+    }
+
+    /// Makes the systems run one after another on the current thread.
+    /// The results are the same either way, so this is mostly useful for debugging & profiling.
+    pub fn set_serial(&mut self, serial: bool) {
+        self.serial = serial;
     }
 
     /// Updates the state of the world with the given closure eagerly.
@@ -69,15 +112,81 @@ impl<R: StateReader> SystemManager<R> {
     }
 
     /// Updates the state of the world with the registered systems.
+    /// If the constraints of the systems contradict each other, the error is logged and the last valid schedule is run,
+    /// without the systems registered since.
     pub fn update_with_systems(&mut self, update_ctx: UpdateContext) {
         let mut cmds = StateCommands::from(&self.state);
         // Take in the removals.
         self.state.transfer_removals(&mut cmds);
-        for s in &mut self.systems {
-            s.update(&update_ctx, &self.state, &mut cmds);
+        if self.schedule_outdated {
+            if let Err(err) = self.build_schedule() {
+                eprintln!(
+                    "could not schedule the systems, keeping the last valid schedule: {}",
+                    err
+                );
+            }
+        }
+        // Every system fills its own fork of the commands, which are joined in the order of the schedule.
+        let mut systems = self.systems.iter_mut().map(Some).collect_vec();
+        let (mut parallel, mut sequential): (Vec<_>, Vec<_>) = self
+            .schedule
+            .iter()
+            .enumerate()
+            .map(|(pos, &i)| {
+                let entry = systems[i].take().unwrap();
+                let mut fork = cmds.fork();
+                if entry.runs_in_parallel() {
+                    fork.run_in_parallel(entry.name());
+                }
+                (pos, entry, fork)
+            })
+            .partition(|(_, entry, _)| entry.runs_in_parallel());
+        let state = &self.state;
+        // Let the systems know what changed since they last ran.
+        let run_system = |entry: &mut SystemEntry<R>, fork: &mut StateCommands| {
+            let ctx = UpdateContext {
+                last_run: entry.last_run,
+                ..update_ctx
+            };
+            entry.system.update(&ctx, state, fork);
+            entry.last_run = state.change_tick();
+        };
+        let mut run_parallel = || {
+            let run = |(_, entry, fork): &mut (usize, &mut SystemEntry<R>, StateCommands)| {
+                run_system(entry, fork);
+            };
+            if self.serial {
+                parallel.iter_mut().for_each(run);
+            } else {
+                parallel.par_iter_mut().for_each(run);
+            }
+        };
+        // The systems that may create entities run one after another, each continuing from the entities created before.
+        let mut run_sequential = || {
+            let mut prev_fork: Option<&StateCommands> = None;
+            for (_, entry, fork) in sequential.iter_mut() {
+                if let Some(prev_fork) = prev_fork {
+                    fork.continue_entities_of(prev_fork);
+                }
+                run_system(entry, fork);
+                prev_fork = Some(fork);
+            }
+        };
+        if self.serial {
+            run_parallel();
+            run_sequential();
+        } else {
+            rayon::join(run_parallel, run_sequential);
         }
-        // Systems should consume all the events.
-        self.state.clear_events();
+        cmds.join_all(
+            parallel
+                .into_iter()
+                .chain(sequential)
+                .sorted_by_key(|(pos, _, _)| *pos)
+                .map(|(_, _, fork)| fork),
+        );
+        // Systems see the events of a single frame.
+        self.state.advance_events();
         self.state.reset_removal_requests();
         self.state.apply_cmds(cmds)
     }
@@ -1,7 +1,10 @@
-use super::EntityRef;
+use std::{collections::HashSet, sync::Mutex};
+
+use notan::math;
+use serde::{Deserialize, Deserializer, Serialize};
 
 /// Represent the transformation of an entity.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Transform {
     pub x: f32,
     pub y: f32,
@@ -41,50 +44,89 @@ impl Transform {
     }
 }
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Offset {
     pub x: f32,
     pub y: f32,
 }
 
 /// Represents the velocity of a component.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Velocity {
     pub x: f32,
     pub y: f32,
 }
 
 /// Represents the velocity that an entity wishes to achieve.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct TargetVelocity {
     pub x: f32,
     pub y: f32,
 }
 
 /// Represents the rotation that an entity wishes to achieve.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct TargetRotation {
     pub deg: f32,
 }
 
 /// Represents the maximum speed achievable by an entity.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct MaxSpeed(pub f32);
 
 /// Represents the acceleration of an entity. Used to determine the rate in which [`Velocity`] will be brought closer to [`TargetVelocity`].
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Acceleration(pub f32);
 
-/// The [`Transform`] of the entities with this component will be fixed to the [`Transform`] of the given parent with an optional offset.
-#[derive(Clone, Copy, Debug)]
-pub struct AnchorTransform(pub EntityRef, pub (f32, f32), pub f32);
+/// The [`Transform`] of the entities with this component will be fixed to the [`Transform`] of their parent with an optional offset & rotation.
+/// The parent is set through [`StateCommands::set_parent`], or by the bundle that the entity is a member of.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AnchorTransform(pub (f32, f32), pub f32);
+
+impl AnchorTransform {
+    /// Returns the transform anchored to the given parent transform.
+    pub fn applied_to(&self, parent_trans: &Transform) -> Transform {
+        let offset = self.0;
+        let rotated_offset = math::Vec2::from_angle(-parent_trans.deg.to_radians())
+            .rotate(math::vec2(offset.0, offset.1));
+        // Translate by the offset.
+        parent_trans
+            .translated((rotated_offset.x, rotated_offset.y))
+            .with_deg(parent_trans.deg + self.1)
+    }
+}
 
 /// A name.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
 pub struct Name(pub &'static str);
 
+/// Names are interned on load, so that each distinct name is leaked at most once.
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        static INTERNED: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+        let loaded = String::deserialize(deserializer)?;
+        let mut interned = INTERNED.lock().unwrap();
+        let interned = interned.get_or_insert_with(HashSet::new);
+        if let Some(existing) = interned.get(loaded.as_str()) {
+            return Ok(Name(existing));
+        }
+        let leaked: &'static str = Box::leak(loaded.into_boxed_str());
+        interned.insert(leaked);
+        Ok(Name(leaked))
+    }
+}
+
 /// Entities with this component will be removed after a period of time.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Lifetime {
     pub remaining_time: f32,
 }
+
+/// A resource that keeps track of the simulated time.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct WorldTime {
+    /// Total simulated time in seconds.
+    pub elapsed: f32,
+    /// Number of simulated frames.
+    pub frame: u64,
+}
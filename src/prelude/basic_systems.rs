@@ -1,5 +1,3 @@
-use notan::math;
-
 use crate::prelude::*;
 
 /// A system that handles simple translation using the velocities.
@@ -9,10 +7,9 @@ pub struct MovementSystem;
 impl<R: StateReader> System<R> for MovementSystem {
     fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         state
-            .select::<(Transform, Velocity)>()
             // No movement for anchored entities!
-            .filter(|(e, _)| state.select_one::<(AnchorTransform,)>(e).is_none())
-            .for_each(|(e, (trans, vel))| {
+            .select::<(Transform, Velocity, Without<AnchorTransform>)>()
+            .for_each(|(e, (trans, vel, _))| {
                 let (mut new_pos_x, mut new_pos_y) = (trans.x, trans.y);
                 let (dx, dy) = (vel.x * ctx.dt, vel.y * ctx.dt);
                 new_pos_x += dx;
@@ -23,6 +20,10 @@ impl<R: StateReader> System<R> for MovementSystem {
                 });
             });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
 
 /// A system that handles accelerating to a target velocity.
@@ -32,10 +33,14 @@ pub struct ApproachVelocitySystem;
 impl<R: StateReader> System<R> for ApproachVelocitySystem {
     fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         state
-            .select::<(Velocity, TargetVelocity, Acceleration)>()
             // No movement for anchored entities!
-            .filter(|(e, _)| state.select_one::<(AnchorTransform,)>(e).is_none())
-            .for_each(|(e, (vel, target_vel, acc))| {
+            .select::<(
+                Velocity,
+                TargetVelocity,
+                Acceleration,
+                Without<AnchorTransform>,
+            )>()
+            .for_each(|(e, (vel, target_vel, acc, _))| {
                 let vel = notan::math::vec2(vel.x, vel.y);
                 let target_vel = notan::math::vec2(target_vel.x, target_vel.y);
                 // who cares ??
@@ -69,6 +74,10 @@ impl<R: StateReader> System<R> for ApproachVelocitySystem {
                 }
             })
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
 
 /// A system that handles rotating to a target rotation.
@@ -78,10 +87,14 @@ pub struct ApproachRotationSystem;
 impl<R: StateReader> System<R> for ApproachRotationSystem {
     fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         state
-            .select::<(Transform, TargetRotation, Acceleration)>()
             // No movement for anchored entities!
-            .filter(|(e, _)| state.select_one::<(AnchorTransform,)>(e).is_none())
-            .for_each(|(e, (trans, target_rot, acc))| {
+            .select::<(
+                Transform,
+                TargetRotation,
+                Acceleration,
+                Without<AnchorTransform>,
+            )>()
+            .for_each(|(e, (trans, target_rot, acc, _))| {
                 let curr_deg = trans.deg;
                 let target_deg = target_rot.deg;
                 let mut diff = target_deg - curr_deg;
@@ -99,31 +112,44 @@ impl<R: StateReader> System<R> for ApproachRotationSystem {
                 });
             })
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
 
 /// A system that handles position and rotation anchoring.
+/// The anchors are followed up the hierarchy, so that the nested anchors are resolved within a single update.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct AnchorSystem;
 
+impl AnchorSystem {
+    /// Returns the transform of the given entity after anchoring it to its ancestors.
+    fn anchored_transform(state: &impl StateReader, e: &EntityRef) -> Option<Transform> {
+        let (trans, anchor) = state.select_one::<(Transform, Optional<AnchorTransform>)>(e)?;
+        match (anchor, state.parent_of(e)) {
+            (Some(anchor), Some(parent)) => {
+                Some(anchor.applied_to(&Self::anchored_transform(state, parent)?))
+            }
+            _ => Some(*trans),
+        }
+    }
+}
+
 impl<R: StateReader> System<R> for AnchorSystem {
     fn update(&mut self, _ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
         state
-            .select::<(AnchorTransform, Transform)>()
-            .for_each(|(child_entity, (anchor, _))| {
-                if !state.is_valid(&anchor.0) {
-                    cmds.remove_component::<AnchorTransform>(&child_entity);
-                } else if let Some((parent_trans,)) = state.select_one::<(Transform,)>(&anchor.0) {
-                    let offset = anchor.1;
-                    let rotated_offset = math::Vec2::from_angle(-parent_trans.deg.to_radians())
-                        .rotate(math::vec2(offset.0, offset.1));
-                    // Translate by the offset.
-                    let new_trans = parent_trans
-                        .translated((rotated_offset.x, rotated_offset.y))
-                        .with_deg(parent_trans.deg + anchor.2);
+            .select::<(AnchorTransform, With<Transform>)>()
+            .for_each(|(child_entity, _)| {
+                if let Some(new_trans) = Self::anchored_transform(state, &child_entity) {
                     cmds.set_component(&child_entity, new_trans);
                 }
             });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
 
 /// A system that handles the entities with a lifetime.
@@ -145,4 +171,28 @@ impl<R: StateReader> System<R> for LifetimeSystem {
             }
         });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
+}
+
+/// A system that advances the [`WorldTime`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorldTimeSystem;
+
+impl<R: StateReader> System<R> for WorldTimeSystem {
+    fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands) {
+        let mut time = state
+            .read_resource::<WorldTime>()
+            .cloned()
+            .unwrap_or_default();
+        time.elapsed += ctx.dt;
+        time.frame += 1;
+        cmds.set_resource(time);
+    }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
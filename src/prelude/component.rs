@@ -1,25 +1,91 @@
-use std::marker::PhantomData;
+use std::{any::TypeId, marker::PhantomData};
+
+use itertools::{Either, Itertools};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::generic_bag::{ConcreteBag, GenericBag, GenericBagMap};
 
-pub use super::component_tuple::ComponentTuple;
+mod component_storage;
+
+pub use super::component_tuple::{Candidates, ComponentTuple};
+pub use component_storage::StorageKind;
+use component_storage::{ComponentTicks, DenseStorage, SparseStorage};
+
+pub trait Component: Clone + std::fmt::Debug + Send + Sync + 'static {}
+impl<T> Component for T where T: Clone + std::fmt::Debug + Send + Sync + 'static {}
 
-pub trait Component: Clone + std::fmt::Debug + 'static {}
-impl<T> Component for T where T: Clone + std::fmt::Debug + 'static {}
+/// Counts the updates applied to a state. Components are stamped with the tick of the update that modified them.
+pub type ChangeTick = u64;
+
+/// The components of a single type, kept in either a dense or a sparse storage.
+#[derive(Clone, Debug)]
+pub(super) struct ComponentVec<T> {
+    storage: Storage<T>,
+    /// The tick to stamp the modifications with.
+    tick: ChangeTick,
+}
 
-/// A vector of components stored contigously in the memory.
 #[derive(Clone, Debug)]
-pub(super) struct ComponentVec<T>(Vec<Option<T>>);
+enum Storage<T> {
+    Dense(DenseStorage<T>),
+    Sparse(SparseStorage<T>),
+}
 
 impl<T: Component> Default for ComponentVec<T> {
     fn default() -> Self {
-        Self(Vec::default())
+        Self {
+            storage: Storage::Dense(Default::default()),
+            tick: 0,
+        }
+    }
+}
+
+/// The serialized form of the components, as `(id, component)` pairs in the order they are iterated.
+/// Sparse storages are marked, dense ones are kept as plain lists of pairs.
+/// Keeping the order of the sparse storages makes the loaded ones iterate like the saved ones, rather than by the ids.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ComponentVecRepr<C> {
+    Dense(Vec<(usize, C)>),
+    Sparse { sparse: Vec<(usize, C)> },
+}
+
+impl<T: Component + Serialize> Serialize for ComponentVec<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let pairs = self.iter().collect_vec();
+        match self.storage {
+            Storage::Dense(_) => ComponentVecRepr::Dense(pairs),
+            Storage::Sparse(_) => ComponentVecRepr::Sparse { sparse: pairs },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Component + Deserialize<'de>> Deserialize<'de> for ComponentVec<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Stamp the loaded components with the first tick, so that they are seen as added since the beginning.
+        let mut components = Self {
+            tick: 1,
+            ..Default::default()
+        };
+        let pairs = match ComponentVecRepr::<T>::deserialize(deserializer)? {
+            ComponentVecRepr::Dense(pairs) => pairs,
+            ComponentVecRepr::Sparse { sparse } => {
+                components.set_storage_kind(StorageKind::Sparse);
+                sparse
+            }
+        };
+        pairs.into_iter().for_each(|(id, c)| components.set(id, c));
+        Ok(components)
     }
 }
 
 impl<T: Component> GenericBag for ComponentVec<T> {
     fn len(&self) -> usize {
-        self.0.len()
+        match &self.storage {
+            Storage::Dense(storage) => storage.num_slots(),
+            Storage::Sparse(storage) => storage.num_slots(),
+        }
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -35,13 +101,26 @@ impl<T: Component> GenericBag for ComponentVec<T> {
             .as_any_mut()
             .downcast_mut::<ComponentVec<T>>()
             .unwrap();
-        self.0
-            .extend(other_component_vec.0.drain(..other_component_vec.0.len()));
+        let other_components = other_component_vec
+            .iter()
+            .map(|(id, c)| (id, c.clone()))
+            .collect_vec();
+        other_components
+            .into_iter()
+            .for_each(|(id, c)| self.set(id, c));
     }
 
     fn remove_at(&mut self, index: usize) -> bool {
         self.remove(index).is_some()
     }
+
+    fn item_type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn set_change_tick(&mut self, tick: ChangeTick) {
+        self.tick = tick;
+    }
 }
 
 impl<T: Component> ConcreteBag for ComponentVec<T> {
@@ -49,12 +128,45 @@ impl<T: Component> ConcreteBag for ComponentVec<T> {
 }
 
 impl<T: Component> ComponentVec<T> {
+    pub(super) fn storage_kind(&self) -> StorageKind {
+        match self.storage {
+            Storage::Dense(_) => StorageKind::Dense,
+            Storage::Sparse(_) => StorageKind::Sparse,
+        }
+    }
+
+    /// Moves the components into the given kind of storage, keeping their ticks.
+    pub(super) fn set_storage_kind(&mut self, kind: StorageKind) {
+        if self.storage_kind() == kind {
+            return;
+        }
+        let storage = std::mem::replace(&mut self.storage, Storage::Dense(Default::default()));
+        self.storage = match storage {
+            Storage::Dense(storage) => Storage::Sparse(storage.into_sparse()),
+            Storage::Sparse(storage) => Storage::Dense(storage.into_dense()),
+        };
+    }
+
+    pub(super) fn num_components(&self) -> usize {
+        match &self.storage {
+            Storage::Dense(storage) => storage.num_components(),
+            Storage::Sparse(storage) => storage.num_components(),
+        }
+    }
+
     pub(super) fn get(&self, id: usize) -> Option<&T> {
-        self.0.get(id).map(|opt_c| opt_c.as_ref()).flatten()
+        match &self.storage {
+            Storage::Dense(storage) => storage.get(id),
+            Storage::Sparse(storage) => storage.get(id),
+        }
     }
 
+    /// Returns the component for modification, marking it as changed.
     pub(super) fn get_mut(&mut self, id: usize) -> Option<&mut T> {
-        self.0.get_mut(id).map(|opt_c| opt_c.as_mut()).flatten()
+        match &mut self.storage {
+            Storage::Dense(storage) => storage.get_mut(id, self.tick),
+            Storage::Sparse(storage) => storage.get_mut(id, self.tick),
+        }
     }
 
     pub(super) fn has(&self, id: usize) -> bool {
@@ -62,30 +174,74 @@ impl<T: Component> ComponentVec<T> {
     }
 
     pub(super) fn set(&mut self, id: usize, c: T) {
-        if id >= self.0.len() {
-            self.0.resize(id + 1, None);
+        match &mut self.storage {
+            Storage::Dense(storage) => storage.set(id, c, self.tick),
+            Storage::Sparse(storage) => storage.set(id, c, self.tick),
         }
-        self.0.get_mut(id).map(|opt_c| opt_c.insert(c));
     }
 
     pub(super) fn remove(&mut self, id: usize) -> Option<T> {
-        self.0.get_mut(id).map(|opt_c| opt_c.take()).flatten()
+        match &mut self.storage {
+            Storage::Dense(storage) => storage.remove(id, self.tick),
+            Storage::Sparse(storage) => storage.remove(id, self.tick),
+        }
+    }
+
+    /// Returns the components along with their ids.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.iter_ticked(|_| true)
+    }
+
+    /// Returns the ids of the entities with the component.
+    pub(super) fn ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+
+    /// Returns the components added after the given tick, along with their ids.
+    pub(super) fn added_since(&self, tick: ChangeTick) -> impl Iterator<Item = (usize, &T)> {
+        self.iter_ticked(move |ticks| ticks.added > tick)
+    }
+
+    /// Returns the components added or changed after the given tick, along with their ids.
+    pub(super) fn changed_since(&self, tick: ChangeTick) -> impl Iterator<Item = (usize, &T)> {
+        self.iter_ticked(move |ticks| ticks.changed > tick)
+    }
+
+    /// Returns the ids of the entities whose components were removed after the given tick, and weren't added back.
+    pub(super) fn removed_since(&self, tick: ChangeTick) -> impl Iterator<Item = usize> + '_ {
+        match &self.storage {
+            Storage::Dense(storage) => Either::Left(storage.iter_removed()),
+            Storage::Sparse(storage) => Either::Right(storage.iter_removed()),
+        }
+        .filter(move |(_, removed)| *removed > tick)
+        .map(|(id, _)| id)
+    }
+
+    fn iter_ticked(
+        &self,
+        pred: impl Fn(&ComponentTicks) -> bool,
+    ) -> impl Iterator<Item = (usize, &T)> {
+        match &self.storage {
+            Storage::Dense(storage) => Either::Left(storage.iter()),
+            Storage::Sparse(storage) => Either::Right(storage.iter()),
+        }
+        .filter(move |(_, _, ticks)| pred(ticks))
+        .map(|(id, c, _)| (id, c))
     }
 }
 
+/// Iterates over the entities matching a component tuple, starting from the candidates of its smallest member.
 pub struct ComponentIter<'a, S: ComponentTuple<'a>> {
     component_mgr: &'a ComponentManager,
-    curr_idx: usize,
-    max_idx: usize,
+    candidates: Candidates<'a>,
     pd: PhantomData<S>,
 }
 
 impl<'a, S: ComponentTuple<'a>> ComponentIter<'a, S> {
-    pub fn new(component_mgr: &'a ComponentManager, max_idx: usize) -> Self {
+    pub fn new(component_mgr: &'a ComponentManager) -> Self {
         Self {
             component_mgr,
-            max_idx,
-            curr_idx: 0,
+            candidates: S::candidates(component_mgr),
             pd: Default::default(),
         }
     }
@@ -95,32 +251,33 @@ impl<'a, S: ComponentTuple<'a>> Iterator for ComponentIter<'a, S> {
     type Item = (usize, S::RefOutput);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.curr_idx > self.max_idx {
-            return None;
-        }
-        let curr_idx = self.curr_idx;
-        self.curr_idx += 1;
-        if S::matches(curr_idx, self.component_mgr) {
-            Some((
-                curr_idx,
-                S::try_fetch(curr_idx, self.component_mgr).unwrap(),
-            ))
-        } else {
-            self.next()
+        loop {
+            let id = self.candidates.next()?;
+            if S::matches(id, self.component_mgr) {
+                return Some((id, S::try_fetch(id, self.component_mgr).unwrap()));
+            }
         }
     }
 }
 
 /// Manages multiple types of components associated with entities.
 #[derive(Default, Debug)]
-pub struct ComponentManager(GenericBagMap);
+pub struct ComponentManager(GenericBagMap, ChangeTick);
 
 impl ComponentManager {
     pub(super) fn get_components_mut<T>(&mut self) -> &mut ComponentVec<T>
     where
         T: Component,
     {
-        self.0.get_bag_mut::<ComponentVec<T>>().unwrap()
+        let bag = self.0.get_bag_mut::<ComponentVec<T>>().unwrap();
+        bag.set_change_tick(self.1);
+        bag
+    }
+
+    /// Sets the tick that the following modifications are stamped with.
+    pub(super) fn set_change_tick(&mut self, tick: ChangeTick) {
+        self.1 = tick;
+        self.0.set_change_tick(tick);
     }
 
     pub(super) fn get_components<T>(&self) -> anyhow::Result<&ComponentVec<T>>
@@ -130,14 +287,68 @@ impl ComponentManager {
         self.0.get_bag::<ComponentVec<T>>()
     }
 
+    /// Returns the component bags along with the type ids of their components.
+    pub(super) fn bags(&self) -> impl Iterator<Item = (&TypeId, &dyn GenericBag)> {
+        self.0
+            .bags
+            .iter()
+            .map(|(type_id, bag)| (type_id, bag.as_ref()))
+    }
+
+    /// Replaces the bag of the components with the given type id.
+    pub(super) fn insert_bag(&mut self, type_id: TypeId, mut bag: Box<dyn GenericBag>) {
+        bag.set_change_tick(self.1);
+        self.0.bags.insert(type_id, bag);
+    }
+
     /// Removes all the components at the given id. Returns true iff the operation succeeds.
     pub(super) fn clear_components(&mut self, id: usize) -> bool {
         self.0.remove_at(id)
     }
 
+    /// Returns one more than the largest entity id with a component.
+    pub(super) fn num_slots(&self) -> usize {
+        self.0.max_len()
+    }
+
+    /// Moves the components of type `T` into the given kind of storage.
+    pub(super) fn set_storage_kind<T: Component>(&mut self, kind: StorageKind) {
+        self.get_components_mut::<T>().set_storage_kind(kind);
+    }
+
     /// Fetches all the components as a tuple.
     pub(super) fn select<'a, S: ComponentTuple<'a>>(&'a self) -> ComponentIter<'a, S> {
-        ComponentIter::new(self, self.0.max_len().saturating_sub(1))
+        ComponentIter::new(self)
+    }
+
+    /// Returns the components of type `T` added after the given tick, along with their ids.
+    pub(super) fn added_since<T: Component>(
+        &self,
+        tick: ChangeTick,
+    ) -> impl Iterator<Item = (usize, &T)> {
+        self.get_components::<T>()
+            .into_iter()
+            .flat_map(move |bag| bag.added_since(tick))
+    }
+
+    /// Returns the components of type `T` added or changed after the given tick, along with their ids.
+    pub(super) fn changed_since<T: Component>(
+        &self,
+        tick: ChangeTick,
+    ) -> impl Iterator<Item = (usize, &T)> {
+        self.get_components::<T>()
+            .into_iter()
+            .flat_map(move |bag| bag.changed_since(tick))
+    }
+
+    /// Returns the ids of the entities whose components of type `T` were removed after the given tick.
+    pub(super) fn removed_since<T: Component>(
+        &self,
+        tick: ChangeTick,
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.get_components::<T>()
+            .into_iter()
+            .flat_map(move |bag| bag.removed_since(tick))
     }
 
     /// Fetches the component tuple associated with the given entity.
@@ -148,3 +359,80 @@ impl ComponentManager {
         S::try_fetch(id, self).ok()
     }
 }
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[test]
+    fn test_change_ticks() {
+        let mut world = SystemManager::from(State::default());
+        let mut entities = vec![];
+        world.update_with(|_, cmds| {
+            entities.push(cmds.create_from((Transform::default(),)));
+            entities.push(cmds.create_from((Transform::default(),)));
+        });
+        let since = world.get_state().change_tick();
+        let changed_ids = |state: &State| {
+            state
+                .changed_since::<Transform>(since)
+                .map(|(e, _)| e.id())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changed_ids(world.get_state()), Vec::<usize>::new());
+        world.update_with(|_, cmds| {
+            cmds.update_component(&entities[1], |trans: &mut Transform| trans.x = 1.);
+        });
+        assert_eq!(changed_ids(world.get_state()), vec![1]);
+        assert_eq!(world.get_state().added_since::<Transform>(since).count(), 0);
+        world.update_with(|_, cmds| cmds.remove_component::<Transform>(&entities[0]));
+        let removed = world
+            .get_state()
+            .removed_since::<Transform>(since)
+            .collect::<Vec<_>>();
+        assert_eq!(removed, vec![entities[0]]);
+    }
+
+    #[test]
+    fn test_sparse_storage() {
+        let mut world = SystemManager::from(State::default());
+        let mut entities = vec![];
+        world.update_with(|_, cmds| {
+            cmds.set_storage::<Velocity>(StorageKind::Sparse);
+            for i in 0..10 {
+                let e = cmds.create_from((Transform::default(),));
+                if i % 3 == 0 {
+                    cmds.set_component(&e, Velocity { x: i as f32, y: 0. });
+                }
+                entities.push(e);
+            }
+        });
+        let velocities = |state: &State| {
+            state
+                .select::<(Transform, Velocity)>()
+                .map(|(e, (_, vel))| (e.id(), vel.x))
+                .sorted_by_key(|(id, _)| *id)
+                .collect_vec()
+        };
+        assert_eq!(
+            velocities(world.get_state()),
+            vec![(0, 0.), (3, 3.), (6, 6.), (9, 9.)]
+        );
+        let since = world.get_state().change_tick();
+        world.update_with(|_, cmds| {
+            cmds.remove_component::<Velocity>(&entities[3]);
+            cmds.set_storage::<Velocity>(StorageKind::Dense);
+        });
+        assert_eq!(
+            velocities(world.get_state()),
+            vec![(0, 0.), (6, 6.), (9, 9.)]
+        );
+        let removed = world
+            .get_state()
+            .removed_since::<Velocity>(since)
+            .collect_vec();
+        assert_eq!(removed, vec![entities[3]]);
+    }
+}
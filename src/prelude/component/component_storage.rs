@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ChangeTick, Component};
+
+/// How the components of a type are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageKind {
+    /// A slot for every entity id up to the largest one.
+    /// The fastest to access, best for the components that most entities have.
+    #[default]
+    Dense,
+    /// A sparse set, where only the entities with the component take up space.
+    /// Best for the components that only a few entities have.
+    Sparse,
+}
+
+/// The ticks of the last modifications to a component slot.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct ComponentTicks {
+    pub(super) added: ChangeTick,
+    pub(super) changed: ChangeTick,
+    pub(super) removed: ChangeTick,
+}
+
+/// Components stored in slots indexed by the entity ids.
+#[derive(Clone, Debug)]
+pub(super) struct DenseStorage<T> {
+    components: Vec<Option<T>>,
+    ticks: Vec<ComponentTicks>,
+    num_components: usize,
+}
+
+impl<T> Default for DenseStorage<T> {
+    fn default() -> Self {
+        Self {
+            components: Default::default(),
+            ticks: Default::default(),
+            num_components: 0,
+        }
+    }
+}
+
+impl<T: Component> DenseStorage<T> {
+    pub(super) fn num_slots(&self) -> usize {
+        self.components.len()
+    }
+
+    pub(super) fn num_components(&self) -> usize {
+        self.num_components
+    }
+
+    pub(super) fn get(&self, id: usize) -> Option<&T> {
+        self.components.get(id)?.as_ref()
+    }
+
+    pub(super) fn get_mut(&mut self, id: usize, tick: ChangeTick) -> Option<&mut T> {
+        let c = self.components.get_mut(id)?.as_mut()?;
+        self.ticks[id].changed = tick;
+        Some(c)
+    }
+
+    pub(super) fn set(&mut self, id: usize, c: T, tick: ChangeTick) {
+        if id >= self.components.len() {
+            self.components.resize(id + 1, None);
+            self.ticks.resize(id + 1, ComponentTicks::default());
+        }
+        let ticks = &mut self.ticks[id];
+        if self.components[id].replace(c).is_none() {
+            self.num_components += 1;
+            ticks.added = tick;
+        }
+        ticks.changed = tick;
+    }
+
+    pub(super) fn remove(&mut self, id: usize, tick: ChangeTick) -> Option<T> {
+        let c = self.components.get_mut(id)?.take()?;
+        self.num_components -= 1;
+        self.ticks[id].removed = tick;
+        Some(c)
+    }
+
+    /// Returns the components along with their ids & ticks.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (usize, &T, &ComponentTicks)> {
+        self.components
+            .iter()
+            .zip(self.ticks.iter())
+            .enumerate()
+            .filter_map(|(id, (opt_c, ticks))| opt_c.as_ref().map(|c| (id, c, ticks)))
+    }
+
+    /// Returns the ids without a component, along with their removal ticks.
+    pub(super) fn iter_removed(&self) -> impl Iterator<Item = (usize, ChangeTick)> + '_ {
+        self.components
+            .iter()
+            .zip(self.ticks.iter())
+            .enumerate()
+            .filter(|(_, (opt_c, ticks))| opt_c.is_none() && ticks.removed > 0)
+            .map(|(id, (_, ticks))| (id, ticks.removed))
+    }
+
+    /// Moves the contents into a sparse storage.
+    pub(super) fn into_sparse(self) -> SparseStorage<T> {
+        let mut sparse = SparseStorage::default();
+        for (id, (opt_c, ticks)) in self.components.into_iter().zip(self.ticks).enumerate() {
+            match opt_c {
+                Some(c) => sparse.push(id, c, ticks),
+                None if ticks.removed > 0 => {
+                    sparse.removed.insert(id, ticks.removed);
+                }
+                None => {}
+            }
+        }
+        sparse
+    }
+}
+
+/// Components packed next to each other, along with a map from the entity ids to their positions.
+#[derive(Clone, Debug)]
+pub(super) struct SparseStorage<T> {
+    ids: Vec<usize>,
+    components: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+    positions: HashMap<usize, usize>,
+    /// The removal ticks of the ids without a component.
+    removed: BTreeMap<usize, ChangeTick>,
+}
+
+impl<T> Default for SparseStorage<T> {
+    fn default() -> Self {
+        Self {
+            ids: Default::default(),
+            components: Default::default(),
+            ticks: Default::default(),
+            positions: Default::default(),
+            removed: Default::default(),
+        }
+    }
+}
+
+impl<T: Component> SparseStorage<T> {
+    pub(super) fn num_slots(&self) -> usize {
+        self.ids.iter().max().map_or(0, |id| id + 1)
+    }
+
+    pub(super) fn num_components(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub(super) fn get(&self, id: usize) -> Option<&T> {
+        self.positions.get(&id).map(|&pos| &self.components[pos])
+    }
+
+    pub(super) fn get_mut(&mut self, id: usize, tick: ChangeTick) -> Option<&mut T> {
+        let pos = *self.positions.get(&id)?;
+        self.ticks[pos].changed = tick;
+        Some(&mut self.components[pos])
+    }
+
+    pub(super) fn set(&mut self, id: usize, c: T, tick: ChangeTick) {
+        if let Some(&pos) = self.positions.get(&id) {
+            self.components[pos] = c;
+            self.ticks[pos].changed = tick;
+        } else {
+            let removed = self.removed.remove(&id).unwrap_or(0);
+            let ticks = ComponentTicks {
+                added: tick,
+                changed: tick,
+                removed,
+            };
+            self.push(id, c, ticks);
+        }
+    }
+
+    pub(super) fn remove(&mut self, id: usize, tick: ChangeTick) -> Option<T> {
+        let pos = self.positions.remove(&id)?;
+        self.ids.swap_remove(pos);
+        self.ticks.swap_remove(pos);
+        let c = self.components.swap_remove(pos);
+        // Point to the new position of the entity that took the place of the removed one.
+        if let Some(&moved_id) = self.ids.get(pos) {
+            self.positions.insert(moved_id, pos);
+        }
+        self.removed.insert(id, tick);
+        Some(c)
+    }
+
+    /// Returns the components along with their ids & ticks.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (usize, &T, &ComponentTicks)> {
+        self.ids
+            .iter()
+            .zip(self.components.iter())
+            .zip(self.ticks.iter())
+            .map(|((&id, c), ticks)| (id, c, ticks))
+    }
+
+    /// Returns the ids without a component, along with their removal ticks.
+    pub(super) fn iter_removed(&self) -> impl Iterator<Item = (usize, ChangeTick)> + '_ {
+        self.removed.iter().map(|(&id, &tick)| (id, tick))
+    }
+
+    /// Moves the contents into a dense storage.
+    pub(super) fn into_dense(self) -> DenseStorage<T> {
+        let mut dense = DenseStorage::default();
+        for ((id, c), ticks) in self.ids.into_iter().zip(self.components).zip(self.ticks) {
+            dense.set(id, c, 0);
+            dense.ticks[id] = ticks;
+        }
+        for (id, removed) in self.removed {
+            if id >= dense.ticks.len() {
+                dense.components.resize(id + 1, None);
+                dense.ticks.resize(id + 1, ComponentTicks::default());
+            }
+            dense.ticks[id].removed = removed;
+        }
+        dense
+    }
+
+    fn push(&mut self, id: usize, c: T, ticks: ComponentTicks) {
+        self.positions.insert(id, self.ids.len());
+        self.ids.push(id);
+        self.components.push(c);
+        self.ticks.push(ticks);
+    }
+}
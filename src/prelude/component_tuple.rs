@@ -1,8 +1,162 @@
+use std::{convert::Infallible, marker::PhantomData};
+
 use super::component::{Component, ComponentManager};
 
+/// The ids of the entities that may match a selection.
+pub type Candidates<'a> = Box<dyn Iterator<Item = usize> + 'a>;
+
+/// A member of a `ComponentTuple`: either a required component, an `Optional` component or a `With`/`Without` filter.
+pub trait ComponentSelector<'a>: Send + 'static {
+    type RefOutput;
+    /// Returns the number of entities that can satisfy this member, if the member narrows down the selection.
+    fn num_candidates(mgr: &'a ComponentManager) -> Option<usize>;
+    /// Returns the ids of the entities that can satisfy this member.
+    fn candidates(mgr: &'a ComponentManager) -> Candidates<'a>;
+    /// Returns true iff the entity satisfies this member.
+    fn matches(entity_id: usize, mgr: &'a ComponentManager) -> bool;
+    /// Materializes the member for the given entity.
+    fn try_fetch(entity_id: usize, mgr: &'a ComponentManager) -> anyhow::Result<Self::RefOutput>;
+    /// Adds the member to the given entity.
+    fn insert(self, entity_id: usize, mgr: &mut ComponentManager);
+}
+
+impl<'a, T: Component> ComponentSelector<'a> for T {
+    type RefOutput = &'a T;
+
+    fn num_candidates(mgr: &'a ComponentManager) -> Option<usize> {
+        Some(
+            mgr.get_components::<T>()
+                .map(|bag| bag.num_components())
+                .unwrap_or(0),
+        )
+    }
+
+    fn candidates(mgr: &'a ComponentManager) -> Candidates<'a> {
+        match mgr.get_components::<T>() {
+            Ok(bag) => Box::new(bag.ids()),
+            Err(_) => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn matches(entity_id: usize, mgr: &'a ComponentManager) -> bool {
+        mgr.get_components::<T>()
+            .map(|bag| bag.has(entity_id))
+            .unwrap_or(false)
+    }
+
+    fn try_fetch(entity_id: usize, mgr: &'a ComponentManager) -> anyhow::Result<Self::RefOutput> {
+        mgr.get_components::<T>()?.get(entity_id).ok_or_else(|| {
+            anyhow::anyhow!(
+                "could not fetch the component {:?} from the bag for the entity id {}",
+                std::any::type_name::<T>(),
+                entity_id
+            )
+        })
+    }
+
+    fn insert(self, entity_id: usize, mgr: &mut ComponentManager) {
+        mgr.get_components_mut::<T>().set(entity_id, self);
+    }
+}
+
+/// Selects the component `T` if the entity has it, without requiring it.
+/// `Option<T>` can't be used for this, as it is a component in its own right.
+///
+/// Only used as a type, hence it can't be constructed.
+pub struct Optional<T: Component>(Infallible, PhantomData<fn() -> T>);
+
+impl<'a, T: Component> ComponentSelector<'a> for Optional<T> {
+    type RefOutput = Option<&'a T>;
+
+    fn num_candidates(_: &'a ComponentManager) -> Option<usize> {
+        None
+    }
+
+    fn candidates(mgr: &'a ComponentManager) -> Candidates<'a> {
+        Box::new(0..mgr.num_slots())
+    }
+
+    fn matches(_: usize, _: &'a ComponentManager) -> bool {
+        true
+    }
+
+    fn try_fetch(entity_id: usize, mgr: &'a ComponentManager) -> anyhow::Result<Self::RefOutput> {
+        Ok(mgr
+            .get_components::<T>()
+            .ok()
+            .and_then(|bag| bag.get(entity_id)))
+    }
+
+    fn insert(self, _: usize, _: &mut ComponentManager) {
+        match self.0 {}
+    }
+}
+
+/// Only selects the entities with the component `T`, without fetching it.
+///
+/// Only used as a type, hence it can't be constructed.
+pub struct With<T: Component>(Infallible, PhantomData<fn() -> T>);
+
+impl<'a, T: Component> ComponentSelector<'a> for With<T> {
+    type RefOutput = ();
+
+    fn num_candidates(mgr: &'a ComponentManager) -> Option<usize> {
+        T::num_candidates(mgr)
+    }
+
+    fn candidates(mgr: &'a ComponentManager) -> Candidates<'a> {
+        T::candidates(mgr)
+    }
+
+    fn matches(entity_id: usize, mgr: &'a ComponentManager) -> bool {
+        T::matches(entity_id, mgr)
+    }
+
+    fn try_fetch(_: usize, _: &'a ComponentManager) -> anyhow::Result<Self::RefOutput> {
+        Ok(())
+    }
+
+    fn insert(self, _: usize, _: &mut ComponentManager) {
+        match self.0 {}
+    }
+}
+
+/// Only selects the entities without the component `T`.
+///
+/// Only used as a type, hence it can't be constructed.
+pub struct Without<T: Component>(Infallible, PhantomData<fn() -> T>);
+
+impl<'a, T: Component> ComponentSelector<'a> for Without<T> {
+    type RefOutput = ();
+
+    fn num_candidates(_: &'a ComponentManager) -> Option<usize> {
+        None
+    }
+
+    fn candidates(mgr: &'a ComponentManager) -> Candidates<'a> {
+        Box::new(0..mgr.num_slots())
+    }
+
+    fn matches(entity_id: usize, mgr: &'a ComponentManager) -> bool {
+        !T::matches(entity_id, mgr)
+    }
+
+    fn try_fetch(_: usize, _: &'a ComponentManager) -> anyhow::Result<Self::RefOutput> {
+        Ok(())
+    }
+
+    fn insert(self, _: usize, _: &mut ComponentManager) {
+        match self.0 {}
+    }
+}
+
 /// A tuple of `Component` objects.
-pub trait ComponentTuple<'a>: Clone + 'static {
+/// When selecting, the members can also be `Optional` components or `With`/`Without` filters,
+/// in which case the tuple should still contain at least one required component.
+pub trait ComponentTuple<'a>: Send + 'static {
     type RefOutput;
+    /// Returns the ids of the entities that may contain these components, taken from the member with the fewest candidates.
+    fn candidates(mgr: &'a ComponentManager) -> Candidates<'a>;
     /// Returns true iff the manager contains an entity with these specific set of components.
     fn matches(entity_id: usize, mgr: &'a ComponentManager) -> bool;
     /// Materializes the components associated with the given entity.
@@ -13,25 +167,70 @@ pub trait ComponentTuple<'a>: Clone + 'static {
 
 // Implement the component tuple trait for all tuples of components.
 variadic_generics::va_expand! { ($va_len:tt) ($($va_idents:ident),+) ($($va_indices:tt),+)
-    impl<'a, $($va_idents: Component),+> ComponentTuple<'a> for ($($va_idents,)+) {
-        type RefOutput = ($(&'a $va_idents,)+);
+    impl<'a, $($va_idents: ComponentSelector<'a>),+> ComponentTuple<'a> for ($($va_idents,)+) {
+        type RefOutput = ($($va_idents::RefOutput,)+);
+
+        fn candidates(mgr: &'a ComponentManager) -> Candidates<'a> {
+            let smallest = [$(($va_idents::num_candidates(mgr), $va_idents::candidates as fn(&'a ComponentManager) -> Candidates<'a>)),+]
+                .iter()
+                .filter_map(|&(num_candidates, candidates)| Some((num_candidates?, candidates)))
+                .min_by_key(|(num_candidates, _)| *num_candidates);
+            match smallest {
+                Some((_, candidates)) => candidates(mgr),
+                // Without a required member, every entity is a candidate.
+                None => Box::new(0..mgr.num_slots()),
+            }
+        }
 
-        fn matches(entity_id: usize, mgr: &ComponentManager) -> bool {
-            $(mgr.get_components::<$va_idents>().map(|bag| bag.has(entity_id)).unwrap_or(false))&&+
+        fn matches(entity_id: usize, mgr: &'a ComponentManager) -> bool {
+            $($va_idents::matches(entity_id, mgr))&&+
         }
 
         fn try_fetch(entity_id: usize, mgr: &'a ComponentManager) -> anyhow::Result<Self::RefOutput> {
-            let out = (
-                $(mgr.get_components::<$va_idents>()?
-                    .get(entity_id)
-                    .ok_or(anyhow::anyhow!("could not fetch the component {:?} from the bag for the entity id {}",
-                        std::any::type_name::<$va_idents>(), entity_id))?,)
-                +);
+            let out = ($($va_idents::try_fetch(entity_id, mgr)?,)+);
             Ok(out)
         }
 
         fn insert(self, entity_id: usize, mgr: &mut ComponentManager) {
-            $(mgr.get_components_mut::<$va_idents>().set(entity_id, self.$va_indices));+
+            $(self.$va_indices.insert(entity_id, mgr));+
         }
     }
 }
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[test]
+    fn test_select_with_filters() {
+        let mut world = SystemManager::from(State::default());
+        world.update_with(|_, cmds| {
+            let parent = cmds.create_from((Transform::default(), Velocity::default()));
+            cmds.create_from((Transform::default(),));
+            let child = cmds.create_from((
+                Transform::default(),
+                Velocity::default(),
+                AnchorTransform((0., 0.), 0.),
+            ));
+            cmds.set_parent(&child, &parent);
+        });
+        let state = world.get_state();
+        let without_anchor = state
+            .select::<(Transform, Without<AnchorTransform>)>()
+            .map(|(e, _)| e.id())
+            .collect::<Vec<_>>();
+        assert_eq!(without_anchor, vec![0, 1]);
+        let with_velocity = state
+            .select::<(Transform, With<Velocity>)>()
+            .map(|(e, _)| e.id())
+            .collect::<Vec<_>>();
+        assert_eq!(with_velocity, vec![0, 2]);
+        let has_velocity = state
+            .select::<(Transform, Optional<Velocity>)>()
+            .map(|(_, (_, vel))| vel.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(has_velocity, vec![true, false, true]);
+    }
+}
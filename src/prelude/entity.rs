@@ -1,7 +1,9 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 
-/// Reference to an entity in the system.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+use serde::{Deserialize, Serialize};
+
+/// Reference to an entity in the system. Ordered by the id, then by the version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct EntityRef {
     id: usize,
     version: u8,
@@ -25,9 +27,10 @@ pub trait EntityRefBag {
     fn remove_invalids(&mut self, entity_mgr: &EntityManager);
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct EntityManager {
-    curr_versions: HashMap<usize, u8>,
+    /// Ordered, so that the saved worlds are the same for the same state.
+    curr_versions: BTreeMap<usize, u8>,
     free_ids: VecDeque<usize>,
     next_id: usize,
 }
@@ -45,6 +48,11 @@ impl EntityManager {
             .map_or(false, |curr_v| curr_v == &e.version)
     }
 
+    /// Returns the number of entities that are currently alive.
+    pub fn num_alive(&self) -> usize {
+        self.next_id - self.free_ids.len()
+    }
+
     /// Creates a new entity and returns a valid reference to it.
     pub(super) fn create(&mut self) -> EntityRef {
         let id = self.free_ids.pop_back().unwrap_or_else(|| {
@@ -33,7 +33,7 @@ variadic_generics::va_expand! { ($va_len:tt) ($($va_idents:ident),+) ($($va_indi
 }
 
 /// Represents a bundle of entities that can be represented as a tuple and can be generated in a single call.
-pub trait EntityBundle<'a>: Sized + Clone + 'static {
+pub trait EntityBundle<'a>: Sized + Clone + Send + 'static {
     /// The corresponding tuple representation of the bundle.
     type TupleRepr: EntityTuple<'a>;
     /// Returns the unique representor of this bundle.
@@ -1,23 +1,46 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashSet, VecDeque},
+    marker::PhantomData,
+};
 
-use super::generic_bag::{ConcreteBag, GenericBag, GenericBagMap};
+use super::{
+    generic_bag::{ConcreteBag, GenericBag, GenericBagMap},
+    StateReader,
+};
 
-pub trait Event: Clone + std::fmt::Debug + 'static {}
-impl<T> Event for T where T: Clone + std::fmt::Debug + 'static {}
+pub trait Event: Clone + std::fmt::Debug + Send + Sync + 'static {}
+impl<T> Event for T where T: Clone + std::fmt::Debug + Send + Sync + 'static {}
 
-/// A vector of events stored contigously in the memory.
+/// The position of an event in the sequence of the events of its type.
+pub type EventSeq = u64;
+
+/// A channel of events, which keeps the emitted events for a number of frames.
 #[derive(Clone, Debug)]
-pub(super) struct EventVec<T>(Vec<T>);
+pub(super) struct EventVec<T> {
+    /// The kept events, oldest first.
+    events: VecDeque<T>,
+    /// The sequence number of the oldest kept event.
+    first_seq: EventSeq,
+    /// The sequence numbers of the first events of the kept frames, oldest first.
+    frame_starts: VecDeque<EventSeq>,
+    /// The number of frames the events are kept for.
+    retention: usize,
+}
 
 impl<T: Event> Default for EventVec<T> {
     fn default() -> Self {
-        Self(Vec::default())
+        Self {
+            events: Default::default(),
+            first_seq: 0,
+            frame_starts: VecDeque::from([0]),
+            retention: 1,
+        }
     }
 }
 
 impl<T: Event> GenericBag for EventVec<T> {
     fn len(&self) -> usize {
-        self.0.len()
+        self.events.len()
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -30,16 +53,27 @@ impl<T: Event> GenericBag for EventVec<T> {
 
     fn merge(&mut self, mut other: Box<dyn GenericBag>) {
         let other_event_vec = other.as_any_mut().downcast_mut::<EventVec<T>>().unwrap();
-        self.0
-            .extend(other_event_vec.0.drain(..other_event_vec.0.len()));
+        self.events.append(&mut other_event_vec.events);
     }
 
     fn remove_at(&mut self, index: usize) -> bool {
-        if index >= self.len() {
-            return false;
+        self.events.remove(index).is_some()
+    }
+
+    fn item_type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn advance_frame(&mut self) {
+        self.frame_starts.push_back(self.next_seq());
+        while self.frame_starts.len() > self.retention {
+            self.frame_starts.pop_front();
         }
-        self.0.remove(index);
-        true
+        // Drop the events of the expired frames.
+        let first_kept_seq = self.frame_starts[0];
+        let num_expired = (first_kept_seq - self.first_seq) as usize;
+        self.events.drain(..num_expired);
+        self.first_seq = first_kept_seq;
     }
 }
 
@@ -50,17 +84,56 @@ impl<T: Event> ConcreteBag for EventVec<T> {
 impl<T: Event> EventVec<T> {
     /// Pushes a new event to this event vector.
     pub(super) fn push(&mut self, evt: T) {
-        self.0.push(evt)
+        self.events.push_back(evt)
+    }
+
+    /// Sets the number of frames the events are kept for. Should be at least one.
+    pub(super) fn set_retention(&mut self, frames: usize) {
+        self.retention = frames.max(1);
+    }
+
+    /// Returns the number of frames the events are kept for.
+    pub(super) fn retention(&self) -> usize {
+        self.retention
+    }
+
+    /// Returns the events of the kept frames, oldest first.
+    pub(super) fn frames(&self) -> impl Iterator<Item = std::collections::vec_deque::Iter<'_, T>> {
+        let ends = self
+            .frame_starts
+            .iter()
+            .skip(1)
+            .copied()
+            .chain([self.next_seq()]);
+        self.frame_starts.iter().zip(ends).map(|(start, end)| {
+            self.events
+                .range((start - self.first_seq) as usize..(end - self.first_seq) as usize)
+        })
     }
 
-    /// Returns an iterator over the elements of this event vector.
-    pub(super) fn iter<'a>(&'a self) -> core::slice::Iter<'a, T> {
-        self.0.iter()
+    /// Returns the sequence number of the next event to be pushed.
+    pub(super) fn next_seq(&self) -> EventSeq {
+        self.first_seq + self.events.len() as EventSeq
+    }
+
+    /// Returns an iterator over the events emitted in the last frame.
+    pub(super) fn iter(&self) -> std::collections::vec_deque::Iter<'_, T> {
+        let last_frame_start = *self.frame_starts.back().unwrap();
+        self.events
+            .range((last_frame_start - self.first_seq) as usize..)
+    }
+
+    /// Returns an iterator over the kept events starting from the given sequence number.
+    pub(super) fn iter_since(&self, seq: EventSeq) -> std::collections::vec_deque::Iter<'_, T> {
+        let start = seq
+            .saturating_sub(self.first_seq)
+            .min(self.events.len() as EventSeq);
+        self.events.range(start as usize..)
     }
 }
 
 /// An iterator that can initialized with `None` to be empty.
-pub struct OptionalIter<'a, T>(Option<core::slice::Iter<'a, T>>);
+pub struct OptionalIter<'a, T>(Option<std::collections::vec_deque::Iter<'a, T>>);
 
 impl<'a, T> Iterator for OptionalIter<'a, T> {
     type Item = &'a T;
@@ -74,12 +147,39 @@ impl<'a, T> Iterator for OptionalIter<'a, T> {
     }
 }
 
+/// Reads the events of type `T` at its own pace, keeping track of the ones it has already read.
+/// The events are kept for the retention period of their type, so the ones that expire before being read are missed.
+#[derive(Debug)]
+pub struct EventReader<T> {
+    next_seq: EventSeq,
+    pd: PhantomData<fn() -> T>,
+}
+
+impl<T: Event> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            next_seq: 0,
+            pd: Default::default(),
+        }
+    }
+}
+
+impl<T: Event> EventReader<T> {
+    /// Returns the events that this reader hasn't read yet, marking them as read.
+    pub fn read<'a, R: StateReader>(&mut self, state: &'a R) -> impl Iterator<Item = &'a T> {
+        let (events, next_seq) = state.read_events_since::<T>(self.next_seq);
+        self.next_seq = next_seq;
+        events
+    }
+}
+
 #[derive(Default, Debug)]
 pub(super) struct EventManager(GenericBagMap);
 
 impl EventManager {
-    pub(super) fn clear_all(&mut self) {
-        self.0.clear()
+    /// Starts a new frame, dropping the events that have been kept for long enough.
+    pub(super) fn advance_frame(&mut self) {
+        self.0.bags.values_mut().for_each(|bag| bag.advance_frame())
     }
 
     pub(super) fn merge_events(&mut self, mut other: EventManager) {
@@ -96,6 +196,19 @@ impl EventManager {
         });
     }
 
+    /// Returns the event vectors along with the type ids of their events.
+    pub(super) fn bags(&self) -> impl Iterator<Item = (&std::any::TypeId, &dyn GenericBag)> {
+        self.0
+            .bags
+            .iter()
+            .map(|(type_id, bag)| (type_id, bag.as_ref()))
+    }
+
+    /// Replaces the event vector of the events with the given type id.
+    pub(super) fn insert_bag(&mut self, type_id: std::any::TypeId, bag: Box<dyn GenericBag>) {
+        self.0.bags.insert(type_id, bag);
+    }
+
     pub(super) fn get_events_mut<T: Event>(&mut self) -> Option<&mut EventVec<T>> {
         self.0.get_bag_mut::<EventVec<T>>().ok()
     }
@@ -111,4 +224,62 @@ impl EventManager {
             OptionalIter(None)
         }
     }
+
+    /// Returns the kept events of type `T` starting from the given sequence number, along with the sequence number of the next event.
+    pub(super) fn get_events_since<T: Event>(
+        &self,
+        seq: EventSeq,
+    ) -> (OptionalIter<'_, T>, EventSeq) {
+        if let Some(evts) = self.get_events::<T>() {
+            (OptionalIter(Some(evts.iter_since(seq))), evts.next_seq())
+        } else {
+            (OptionalIter(None), seq)
+        }
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+    #[allow(unused_imports)]
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    struct TestEvt(usize);
+
+    #[test]
+    fn test_event_retention() {
+        let mut world = SystemManager::from(State::default());
+        world.update_with(|_, cmds| cmds.set_event_retention::<TestEvt>(2));
+        let mut reader = EventReader::<TestEvt>::default();
+        for i in 0..3 {
+            world.update_with(|_, cmds| cmds.emit_event(TestEvt(i)));
+            world.update_with_systems(Default::default());
+        }
+        // Systems only see the events of the last frame.
+        let last_frame = world
+            .get_state()
+            .read_events::<TestEvt>()
+            .copied()
+            .collect_vec();
+        assert_eq!(last_frame, vec![]);
+        // The kept frames & the retention are saved with the snapshots.
+        let mut registry = SnapshotRegistry::default();
+        registry.register_event::<TestEvt>();
+        let snapshot = world.get_state().to_snapshot(&registry).unwrap();
+        let mut loaded = SystemManager::from(State::from_snapshot(snapshot, &registry).unwrap());
+        // The reader still sees the events of the previous frame, the older ones have expired.
+        let read = reader.read(world.get_state()).copied().collect_vec();
+        assert_eq!(read, vec![TestEvt(2)]);
+        assert_eq!(reader.read(world.get_state()).count(), 0);
+        let mut reader = EventReader::<TestEvt>::default();
+        let read = reader.read(loaded.get_state()).copied().collect_vec();
+        assert_eq!(read, vec![TestEvt(2)]);
+        loaded.update_with(|_, cmds| cmds.emit_event(TestEvt(3)));
+        loaded.update_with_systems(Default::default());
+        let read = reader.read(loaded.get_state()).copied().collect_vec();
+        assert_eq!(read, vec![TestEvt(3)]);
+    }
 }
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use itertools::Itertools;
 
 /// Represents a generic bag of objects.
-pub trait GenericBag: std::fmt::Debug {
+pub trait GenericBag: std::fmt::Debug + Send + Sync {
     /// Returns the size of the collection.
     fn len(&self) -> usize;
     /// Returns itself as an `Any` reference, which can be used to safely cast into the underlying concrete bag.
@@ -14,6 +14,12 @@ pub trait GenericBag: std::fmt::Debug {
     fn merge(&mut self, other: Box<dyn GenericBag>);
     /// Removes the value at the given index.
     fn remove_at(&mut self, index: usize) -> bool;
+    /// Returns the type name of the stored objects.
+    fn item_type_name(&self) -> &'static str;
+    /// Sets the tick that the following modifications are stamped with. Only meaningful for the bags that track changes.
+    fn set_change_tick(&mut self, _tick: u64) {}
+    /// Starts a new frame, dropping the expired objects. Only meaningful for the bags that keep objects across frames.
+    fn advance_frame(&mut self) {}
 }
 
 /// Represents a concrete bag of objects that can be stored safely as a `GenericStorage`.
@@ -34,25 +40,31 @@ impl GenericBagMap {
             .or_insert(Box::new(C::default()))
             .as_any_mut()
             .downcast_mut::<C>()
-            .ok_or(anyhow::anyhow!(
-                "could not downcast the generic bag to {:?}",
-                std::any::type_name::<C>()
-            ))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "could not downcast the generic bag to {:?}",
+                    std::any::type_name::<C>()
+                )
+            })
     }
 
     pub fn get_bag<C: ConcreteBag>(&self) -> anyhow::Result<&C> {
         self.bags
             .get(&std::any::TypeId::of::<C::Item>())
-            .ok_or(anyhow::anyhow!(
-                "generic bag for {:?} doesn't exist",
-                std::any::type_name::<C::Item>()
-            ))?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "generic bag for {:?} doesn't exist",
+                    std::any::type_name::<C::Item>()
+                )
+            })?
             .as_any()
             .downcast_ref::<C>()
-            .ok_or(anyhow::anyhow!(
-                "could not downcast the generic bag to {:?}",
-                std::any::type_name::<C>()
-            ))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "could not downcast the generic bag to {:?}",
+                    std::any::type_name::<C>()
+                )
+            })
     }
 
     pub fn max_len(&self) -> usize {
@@ -63,8 +75,10 @@ impl GenericBagMap {
             .unwrap_or(0)
     }
 
-    pub fn clear(&mut self) {
-        self.bags.clear()
+    pub fn set_change_tick(&mut self, tick: u64) {
+        self.bags
+            .values_mut()
+            .for_each(|bag| bag.set_change_tick(tick))
     }
 
     pub fn remove_at(&mut self, index: usize) -> bool {
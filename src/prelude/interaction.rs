@@ -1,7 +1,8 @@
-use std::{any::TypeId, collections::HashSet, marker::PhantomData};
+use std::{any::TypeId, collections::BTreeSet, marker::PhantomData};
 
 use itertools::Itertools;
-use rand::random;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 mod interaction_acceptor;
 mod interaction_delegate;
@@ -12,7 +13,7 @@ pub use interaction_delegate::*;
 use crate::prelude::*;
 
 /// Represents an interaction that can occur between two entities in the game.
-pub trait Interaction: 'static + std::fmt::Debug + Clone {
+pub trait Interaction: 'static + std::fmt::Debug + Clone + Send + Sync {
     fn priority() -> usize;
     fn can_start_targeted(actor: &EntityRef, target: &EntityRef, state: &impl StateReader) -> bool;
     fn can_start_untargeted(
@@ -40,9 +41,9 @@ pub trait Interaction: 'static + std::fmt::Debug + Clone {
 }
 
 /// Denotes an interactable entity as the target of the interaction `I`.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InteractTarget<I: Interaction> {
-    pub actors: HashSet<EntityRef>,
+    pub actors: BTreeSet<EntityRef>,
     pd: PhantomData<I>,
 }
 
@@ -69,6 +70,50 @@ impl<I: Interaction> Default for InteractTarget<I> {
     }
 }
 
+/// Denotes an entity as the actor of the interaction `I`, indexing the targets it interacts with.
+/// Kept in sync with the [`InteractTarget`]s by the [`InteractionSystem`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InteractActor<I: Interaction> {
+    pub targets: BTreeSet<EntityRef>,
+    pd: PhantomData<I>,
+}
+
+impl<I: Interaction> EntityRefBag for InteractActor<I> {
+    fn remove_invalids(&mut self, entity_mgr: &EntityManager) {
+        self.targets.retain(|target| entity_mgr.is_valid(target));
+    }
+}
+
+impl<I: Interaction> Default for InteractActor<I> {
+    fn default() -> Self {
+        Self {
+            targets: Default::default(),
+            pd: Default::default(),
+        }
+    }
+}
+
+/// Provides insights about the interactions of the actors.
+pub trait InteractionInsights<'a> {
+    /// Returns the targets that the given actor is interacting with through the interaction `I`.
+    fn interactions_of<I: Interaction>(
+        &self,
+        actor: &EntityRef,
+    ) -> impl Iterator<Item = EntityRef> + 'a;
+}
+
+impl<'a, R: StateReader> InteractionInsights<'a> for StateInsights<'a, R> {
+    fn interactions_of<I: Interaction>(
+        &self,
+        actor: &EntityRef,
+    ) -> impl Iterator<Item = EntityRef> + 'a {
+        self.0
+            .select_one::<(InteractActor<I>,)>(actor)
+            .into_iter()
+            .flat_map(|(actor_intr,)| actor_intr.targets.iter().copied())
+    }
+}
+
 /// A request to explicitly start an interaction.
 #[derive(Clone, Copy, Debug)]
 pub struct TryInteractReq {
@@ -78,9 +123,9 @@ pub struct TryInteractReq {
 }
 
 impl TryInteractReq {
-    pub fn new(actor: EntityRef, target: EntityRef) -> Self {
+    pub fn new(actor: EntityRef, target: EntityRef, rng: &mut impl Rng) -> Self {
         Self {
-            consensus_id: random(),
+            consensus_id: rng.gen(),
             actor,
             target,
         }
@@ -95,9 +140,9 @@ pub struct TryUninteractReq {
     pub target: EntityRef,
 }
 impl TryUninteractReq {
-    pub fn new(actor: EntityRef, target: EntityRef) -> Self {
+    pub fn new(actor: EntityRef, target: EntityRef, rng: &mut impl Rng) -> Self {
         Self {
-            consensus_id: random(),
+            consensus_id: rng.gen(),
             actor,
             target,
         }
@@ -212,7 +257,7 @@ impl<I: Interaction, R: StateReader> System<R> for InteractionSystem<I> {
         let invalidated_interactions = Self::interactions(state).filter(|(actor, target)| {
             state.will_be_removed(actor) || state.will_be_removed(target)
         });
-        let to_end: HashSet<_> = invalidated_interactions
+        let to_end: BTreeSet<_> = invalidated_interactions
             // End the accepted uninteract proposals.
             .chain(
                 state
@@ -236,9 +281,12 @@ impl<I: Interaction, R: StateReader> System<R> for InteractionSystem<I> {
                 cmds.update_component(&target, move |interactable: &mut InteractTarget<I>| {
                     interactable.actors.remove(&actor);
                 });
+                cmds.update_component(&actor, move |actor_intr: &mut InteractActor<I>| {
+                    actor_intr.targets.remove(&target);
+                });
             }
         });
-        let to_start: HashSet<_> = state
+        let to_start: BTreeSet<_> = state
             .read_events::<InteractAcceptedEvt>()
             .filter(|evt| evt.proposer_tid == TypeId::of::<I>())
             .map(|evt| (evt.actor, evt.target))
@@ -258,6 +306,10 @@ impl<I: Interaction, R: StateReader> System<R> for InteractionSystem<I> {
             cmds.update_component(&target, move |interactable: &mut InteractTarget<I>| {
                 interactable.actors.insert(actor);
             });
+            // Index the interaction on the actor side as well.
+            cmds.update_component_or_default(&actor, move |actor_intr: &mut InteractActor<I>| {
+                actor_intr.targets.insert(target);
+            });
         });
         // Propose interactions in response to untargeted interact/uninteract requests.
         state.read_events::<TryInteractReq>().for_each(|evt| {
@@ -275,6 +327,9 @@ impl<I: Interaction, R: StateReader> System<R> for InteractionSystem<I> {
         state.select::<(InteractTarget<I>,)>().for_each(|(e, _)| {
             cmds.remove_invalids::<InteractTarget<I>>(&e);
         });
+        state.select::<(InteractActor<I>,)>().for_each(|(e, _)| {
+            cmds.remove_invalids::<InteractActor<I>>(&e);
+        });
     }
 }
 
@@ -354,3 +409,54 @@ impl<I: Interaction> InteractionEndedEvt<I> {
         }
     }
 }
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct TestIntr;
+
+    impl Interaction for TestIntr {
+        fn priority() -> usize {
+            0
+        }
+
+        fn can_start_targeted(_: &EntityRef, _: &EntityRef, _: &impl StateReader) -> bool {
+            true
+        }
+
+        fn can_start_untargeted(_: &EntityRef, _: &EntityRef, _: &impl StateReader) -> bool {
+            true
+        }
+
+        fn can_end_untargeted(_: &EntityRef, _: &EntityRef, _: &impl StateReader) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_interactions_of() {
+        let mut world = SystemManager::from(State::default());
+        world.register_system(InteractionSystem::<TestIntr>::default());
+        let mut entities = None;
+        world.update_with(|_, cmds| {
+            let actor = cmds.create_entity();
+            let target = cmds.create_from((InteractTarget::<TestIntr>::default(),));
+            cmds.emit_event(InteractReq::<TestIntr>::new(actor, target));
+            entities = Some((actor, target));
+        });
+        let (actor, target) = entities.unwrap();
+        world.update_with_systems(Default::default());
+        let insights = StateInsights::of(world.get_state());
+        assert_eq!(
+            insights.interactions_of::<TestIntr>(&actor).collect_vec(),
+            vec![target]
+        );
+        assert_eq!(insights.interactions_of::<TestIntr>(&target).count(), 0);
+        world.update_with(|_, cmds| cmds.emit_event(UninteractReq::<TestIntr>::new(actor, target)));
+        world.update_with_systems(Default::default());
+        let insights = StateInsights::of(world.get_state());
+        assert_eq!(insights.interactions_of::<TestIntr>(&actor).count(), 0);
+    }
+}
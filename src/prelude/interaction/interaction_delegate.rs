@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
 use super::{TryInteractReq, TryUninteractReq};
 
 /// A component that converts untargeted interact/uninteract requests to its parent while acting as a target.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct UntargetedInteractionDelegate(pub EntityRef);
 
 #[derive(Clone, Copy, Debug)]
@@ -24,14 +26,16 @@ impl<R: StateReader> System<R> for UntargetedInteractionDelegateSystem {
             if let Some((target_delegate,)) =
                 state.select_one::<(UntargetedInteractionDelegate,)>(&evt.target)
             {
-                cmds.emit_event(TryUninteractReq::new(evt.actor, target_delegate.0));
+                let req = TryUninteractReq::new(evt.actor, target_delegate.0, cmds.rng());
+                cmds.emit_event(req);
             }
         });
         state.read_events::<TryInteractReq>().for_each(|evt| {
             if let Some((target_delegate,)) =
                 state.select_one::<(UntargetedInteractionDelegate,)>(&evt.target)
             {
-                cmds.emit_event(TryInteractReq::new(evt.actor, target_delegate.0));
+                let req = TryInteractReq::new(evt.actor, target_delegate.0, cmds.rng());
+                cmds.emit_event(req);
             }
         });
     }
@@ -0,0 +1,70 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A world-wide singleton stored in the state, such as the player entity or the world time.
+pub trait Resource: Clone + std::fmt::Debug + Send + Sync + 'static {}
+impl<T> Resource for T where T: Clone + std::fmt::Debug + Send + Sync + 'static {}
+
+/// A type-erased resource.
+pub trait GenericResource: std::fmt::Debug + Send + Sync {
+    /// Returns itself as an `Any` reference, which can be used to safely cast into the underlying resource.
+    fn as_any(&self) -> &dyn Any;
+    /// Returns itself as a mutable `Any` reference, which can be used to safely cast into the underlying resource.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Returns the type name of the underlying resource.
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T: Resource> GenericResource for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
+
+/// Keeps at most a single instance of each resource type.
+#[derive(Default, Debug)]
+pub struct ResourceManager {
+    resources: HashMap<TypeId, Box<dyn GenericResource>>,
+}
+
+impl ResourceManager {
+    pub(super) fn get<T: Resource>(&self) -> Option<&T> {
+        self.resources
+            .get(&TypeId::of::<T>())
+            .and_then(|res| res.as_ref().as_any().downcast_ref::<T>())
+    }
+
+    pub(super) fn get_mut<T: Resource>(&mut self) -> Option<&mut T> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|res| res.as_any_mut().downcast_mut::<T>())
+    }
+
+    pub(super) fn set<T: Resource>(&mut self, res: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(res));
+    }
+
+    pub(super) fn remove<T: Resource>(&mut self) {
+        self.resources.remove(&TypeId::of::<T>());
+    }
+
+    /// Returns the resources along with their type ids.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (&TypeId, &dyn GenericResource)> {
+        self.resources
+            .iter()
+            .map(|(type_id, res)| (type_id, res.as_ref()))
+    }
+
+    /// Replaces the resource with the given type id.
+    pub(super) fn insert_boxed(&mut self, type_id: TypeId, res: Box<dyn GenericResource>) {
+        self.resources.insert(type_id, res);
+    }
+}
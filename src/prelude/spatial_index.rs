@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use super::EntityRef;
+
+/// The size of the square cells of the grid.
+const CELL_SIZE: f32 = 128.;
+
+/// The coordinates of a cell in the grid.
+type Cell = (i32, i32);
+
+/// A uniform grid over the positions of the entities with a [`Transform`](super::Transform).
+/// Kept up to date by the state as the transforms change, see [`SpatialInsights`](super::SpatialInsights) for the queries.
+#[derive(Clone, Debug, Default)]
+pub struct SpatialIndex {
+    /// The entities in each non-empty cell, in the order they moved in.
+    cells: HashMap<Cell, Vec<EntityRef>>,
+    /// The indexed positions, by the entity ids.
+    positions: Vec<Option<(f32, f32)>>,
+    num_entities: usize,
+}
+
+impl SpatialIndex {
+    fn cell_of(pos: (f32, f32)) -> Cell {
+        (
+            (pos.0 / CELL_SIZE).floor() as i32,
+            (pos.1 / CELL_SIZE).floor() as i32,
+        )
+    }
+
+    fn dist_sq(p1: (f32, f32), p2: (f32, f32)) -> f32 {
+        let (dx, dy) = (p1.0 - p2.0, p1.1 - p2.1);
+        dx * dx + dy * dy
+    }
+
+    /// Returns the indexed position of the given entity.
+    pub fn position_of(&self, e: &EntityRef) -> Option<(f32, f32)> {
+        *self.positions.get(e.id())?
+    }
+
+    /// Indexes the entity at the given position, moving it if it is already indexed.
+    pub(super) fn set(&mut self, e: EntityRef, pos: (f32, f32)) {
+        if e.id() >= self.positions.len() {
+            self.positions.resize(e.id() + 1, None);
+        }
+        match self.positions[e.id()].replace(pos) {
+            Some(prev_pos) if Self::cell_of(prev_pos) == Self::cell_of(pos) => {
+                // Still in the same cell, but the reference might be of a newer version.
+                let entities = self.cells.get_mut(&Self::cell_of(pos)).unwrap();
+                if let Some(entry) = entities.iter_mut().find(|entry| entry.id() == e.id()) {
+                    *entry = e;
+                }
+                return;
+            }
+            Some(prev_pos) => self.remove_from_cell(e.id(), Self::cell_of(prev_pos)),
+            None => self.num_entities += 1,
+        }
+        self.cells.entry(Self::cell_of(pos)).or_default().push(e);
+    }
+
+    /// Drops the entity with the given id from the index.
+    pub(super) fn remove(&mut self, id: usize) {
+        if let Some(pos) = self.positions.get_mut(id).and_then(Option::take) {
+            self.remove_from_cell(id, Self::cell_of(pos));
+            self.num_entities -= 1;
+        }
+    }
+
+    fn remove_from_cell(&mut self, id: usize, cell: Cell) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|e| e.id() != id);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Returns the entities along with their positions in the given range of cells.
+    fn in_cells(
+        &self,
+        min_cell: Cell,
+        max_cell: Cell,
+    ) -> impl Iterator<Item = (EntityRef, (f32, f32))> + '_ {
+        (min_cell.1..=max_cell.1)
+            .flat_map(move |y| (min_cell.0..=max_cell.0).map(move |x| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .map(|e| (*e, self.positions[e.id()].unwrap()))
+    }
+
+    /// Returns the entities within the given rectangle.
+    pub fn within_rect(
+        &self,
+        top_left: (f32, f32),
+        bottom_right: (f32, f32),
+    ) -> impl Iterator<Item = EntityRef> + '_ {
+        self.in_cells(Self::cell_of(top_left), Self::cell_of(bottom_right))
+            .filter(move |(_, pos)| {
+                (top_left.0..=bottom_right.0).contains(&pos.0)
+                    && (top_left.1..=bottom_right.1).contains(&pos.1)
+            })
+            .map(|(e, _)| e)
+    }
+
+    /// Returns the entities within the given distance of the center.
+    pub fn within_radius(
+        &self,
+        center: (f32, f32),
+        radius: f32,
+    ) -> impl Iterator<Item = EntityRef> + '_ {
+        let min_cell = Self::cell_of((center.0 - radius, center.1 - radius));
+        let max_cell = Self::cell_of((center.0 + radius, center.1 + radius));
+        self.in_cells(min_cell, max_cell)
+            .filter(move |(_, pos)| Self::dist_sq(center, *pos) <= radius * radius)
+            .map(|(e, _)| e)
+    }
+
+    /// Returns up to `k` entities accepted by the filter, the nearest to the center first.
+    /// Searches the cells in rings around the center, stopping once the remaining rings can't contain a nearer entity.
+    pub fn k_nearest(
+        &self,
+        center: (f32, f32),
+        k: usize,
+        mut filter: impl FnMut(&EntityRef) -> bool,
+    ) -> Vec<EntityRef> {
+        let (cx, cy) = Self::cell_of(center);
+        let mut nearest: Vec<(f32, EntityRef)> = Vec::new();
+        let mut num_visited = 0;
+        let mut ring = 0;
+        while k > 0 && num_visited < self.num_entities {
+            // The entities in this ring are at least this far from the center.
+            let min_dist = (ring - 1).max(0) as f32 * CELL_SIZE;
+            if nearest.len() == k && nearest[k - 1].0 <= min_dist * min_dist {
+                break;
+            }
+            let ring_cells = if ring == 0 {
+                vec![(cx, cy)]
+            } else {
+                (-ring..=ring)
+                    .flat_map(|d| [(cx + d, cy - ring), (cx + d, cy + ring)])
+                    .chain(
+                        (1 - ring..ring).flat_map(|d| [(cx - ring, cy + d), (cx + ring, cy + d)]),
+                    )
+                    .collect()
+            };
+            for cell in ring_cells {
+                let Some(entities) = self.cells.get(&cell) else {
+                    continue;
+                };
+                num_visited += entities.len();
+                nearest.extend(
+                    entities
+                        .iter()
+                        .filter(|e| filter(e))
+                        .map(|e| (Self::dist_sq(center, self.positions[e.id()].unwrap()), *e)),
+                );
+            }
+            nearest.sort_by(|(d1, e1), (d2, e2)| d1.total_cmp(d2).then(e1.id().cmp(&e2.id())));
+            nearest.truncate(k);
+            ring += 1;
+        }
+        nearest.into_iter().map(|(_, e)| e).collect()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[test]
+    fn test_spatial_queries() {
+        let mut world = SystemManager::from(State::default());
+        let mut entities = vec![];
+        world.update_with(|_, cmds| {
+            entities = [(0., 0.), (100., 0.), (300., 300.), (-500., 20.)]
+                .into_iter()
+                .map(|(x, y)| cmds.create_from((Transform::at(x, y),)))
+                .collect();
+            cmds.set_component(&entities[3], Velocity::default());
+        });
+        let sorted_ids =
+            |entities: Vec<EntityRef>| entities.iter().map(EntityRef::id).sorted().collect_vec();
+        let insights = StateInsights::of(world.get_state());
+        assert_eq!(
+            sorted_ids(insights.within_radius((0., 0.), 150.).collect()),
+            vec![0, 1]
+        );
+        assert_eq!(
+            sorted_ids(insights.within_rect((50., -10.), (400., 400.)).collect()),
+            vec![1, 2]
+        );
+        assert_eq!(
+            insights.k_nearest::<(Transform,)>((250., 250.), 2, |_| true),
+            vec![entities[2], entities[1]]
+        );
+        assert_eq!(
+            insights.nearest::<(Transform,)>((0., 0.), |e| *e != entities[0]),
+            Some(entities[1])
+        );
+        assert_eq!(
+            insights.nearest::<(Velocity,)>((0., 0.), |_| true),
+            Some(entities[3])
+        );
+        // The index follows the moved & removed entities.
+        world.update_with(|_, cmds| {
+            cmds.set_component(&entities[2], Transform::at(10., 10.));
+            cmds.remove_component::<Transform>(&entities[1]);
+        });
+        let insights = StateInsights::of(world.get_state());
+        assert_eq!(
+            sorted_ids(insights.within_radius((0., 0.), 150.).collect()),
+            vec![0, 2]
+        );
+    }
+}
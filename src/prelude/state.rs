@@ -1,54 +1,92 @@
-use std::collections::HashSet;
+use std::{collections::BTreeSet, sync::Arc};
 
 use itertools::Itertools;
 use notan::egui::epaint::ahash::HashMap;
+use rand::RngCore;
 
 use super::{
-    component::{Component, ComponentIter, ComponentManager, ComponentTuple},
-    event::{Event, EventManager, OptionalIter},
-    EntityBundle, EntityManager, EntityRef, EntityRefBag, EntityTuple,
+    component::{
+        ChangeTick, Component, ComponentIter, ComponentManager, ComponentTuple, StorageKind,
+    },
+    event::{Event, EventManager, EventSeq, OptionalIter},
+    resource::{Resource, ResourceManager},
+    EntityBundle, EntityManager, EntityRef, EntityRefBag, EntityTuple, SpatialIndex, Transform,
+    WorldRng,
 };
 
+mod derived_cache;
+mod hierarchy;
 mod state_reader;
+mod state_snapshot;
+
+use derived_cache::DerivedCache;
+use hierarchy::Hierarchy;
 
 pub use state_reader::*;
+pub use state_snapshot::*;
 
 #[derive(Default, Debug)]
 pub struct State {
     component_mgr: ComponentManager,
     entity_mgr: EntityManager,
     event_mgr: EventManager,
-    to_remove: HashSet<EntityRef>,
+    /// Ordered, so that the removed ids are freed in the same order on every run.
+    to_remove: BTreeSet<EntityRef>,
     bundles: HashMap<EntityRef, Vec<EntityRef>>,
+    hierarchy: Hierarchy,
+    spatial_index: SpatialIndex,
+    resource_mgr: ResourceManager,
+    rng: WorldRng,
+    change_tick: ChangeTick,
+    derived: DerivedCache,
 }
 
 impl State {
-    /// Marks the given entity for removal.
+    /// Returns the reference of the entity with the given id, as of its current version.
+    fn entity_ref_of(&self, id: usize) -> EntityRef {
+        EntityRef::new(id, self.entity_mgr.get_curr_version(id).unwrap_or(0))
+    }
+
+    /// Moves the entities whose transforms were modified after the given tick in the spatial index.
+    fn update_spatial_index(&mut self, since: ChangeTick) {
+        for id in self.component_mgr.removed_since::<Transform>(since) {
+            self.spatial_index.remove(id);
+        }
+        for (id, trans) in self.component_mgr.changed_since::<Transform>(since) {
+            let e = EntityRef::new(id, self.entity_mgr.get_curr_version(id).unwrap_or(0));
+            self.spatial_index.set(e, (trans.x, trans.y));
+        }
+    }
+
+    /// Marks the given entity for removal, along with its descendants.
     fn mark_for_removal(&mut self, e: &EntityRef) {
-        // If the entity is part of a bundle, remove the bundle altogether.
-        let containing_bundle = self
-            .bundles
-            .iter()
-            .find(|(_, bundle_entities)| bundle_entities.contains(e));
-        if let Some((&bundle_key, _)) = containing_bundle {
-            // Recurse on the parent key (go up in the parent tree)
-            if !self.to_remove.contains(&bundle_key) {
-                return self.mark_for_removal(&bundle_key);
+        // If the entity is a member of a bundle, remove the bundle altogether (go up).
+        if let Some(&parent) = self.hierarchy.parent_of(e) {
+            let is_bundle_member = self
+                .bundles
+                .get(&parent)
+                .is_some_and(|bundle_entities| bundle_entities.contains(e));
+            if is_bundle_member && !self.to_remove.contains(&parent) {
+                return self.mark_for_removal(&parent);
             }
         }
-        // Otherwise, remove the entity and it's own bundle (go down)
+        // Otherwise, remove the entity and its children (go down).
         self.to_remove.insert(*e);
-        self.bundles.get(e).cloned().map(|bundle_entities| {
-            bundle_entities.into_iter().for_each(|e| {
-                self.mark_for_removal(&e);
-            });
-        });
+        for child in self.hierarchy.children_of(e).to_vec() {
+            if !self.to_remove.contains(&child) {
+                self.mark_for_removal(&child);
+            }
+        }
     }
 
-    /// Registers a new bundle.
+    /// Registers a new bundle, attaching its members to the primary entity.
     fn push_bundle<'a, B: EntityBundle<'a>>(&mut self, bundle: B) {
         let bundle_key = *bundle.primary_entity();
         let bundle_vec = Vec::from_iter(bundle.deconstruct().into_array().into_iter());
+        for member in bundle_vec.iter().filter(|&member| *member != bundle_key) {
+            // The members are never the ancestors of the primary entity, so this never forms a cycle.
+            self.hierarchy.set_parent(*member, bundle_key).ok();
+        }
         self.bundles.insert(bundle_key, bundle_vec);
     }
 }
@@ -89,6 +127,12 @@ impl StateReader for State {
         self.event_mgr.get_events_iter()
     }
 
+    /// Returns an iterator over the kept events of the given type starting from the given sequence number,
+    /// along with the sequence number of the next event.
+    fn read_events_since<T: Event>(&self, seq: EventSeq) -> (Self::EventIterator<'_, T>, EventSeq) {
+        self.event_mgr.get_events_since(seq)
+    }
+
     /// Returns an iterator over the components identified by the given component selector.
     fn select<'a, S: ComponentTuple<'a>>(&'a self) -> Self::ComponentIterator<'a, S> {
         EntityRefComponentIter(self.component_mgr.select::<S>(), &self.entity_mgr)
@@ -113,22 +157,86 @@ impl StateReader for State {
         Some(bundle)
     }
 
+    /// Returns the parent of the given entity, if it has one.
+    fn parent_of(&self, e: &EntityRef) -> Option<&EntityRef> {
+        self.hierarchy.parent_of(e)
+    }
+
+    /// Returns the children of the given entity, in the order they were attached.
+    fn children_of(&self, e: &EntityRef) -> &[EntityRef] {
+        self.hierarchy.children_of(e)
+    }
+
+    /// Returns the index of the positions of the entities.
+    fn spatial_index(&self) -> &SpatialIndex {
+        &self.spatial_index
+    }
+
+    /// Returns the resource of the given type, if it exists.
+    fn read_resource<T: Resource>(&self) -> Option<&T> {
+        self.resource_mgr.get::<T>()
+    }
+
+    /// Returns the value of type `T` derived from the state, building it at most once per applied update.
+    fn derived<T: Send + Sync + 'static>(&self, build: impl FnOnce(&Self) -> T) -> Arc<T> {
+        self.derived.get_or_build(self.change_tick, || build(self))
+    }
+
+    /// Returns the tick of the last applied update.
+    fn change_tick(&self) -> ChangeTick {
+        self.change_tick
+    }
+
+    /// Returns the entities whose component `T` was added after the given tick, along with the component.
+    fn added_since<T: Component>(&self, tick: ChangeTick) -> impl Iterator<Item = (EntityRef, &T)> {
+        self.component_mgr
+            .added_since::<T>(tick)
+            .map(|(id, c)| (self.entity_ref_of(id), c))
+    }
+
+    /// Returns the entities whose component `T` was added or changed after the given tick, along with the component.
+    fn changed_since<T: Component>(
+        &self,
+        tick: ChangeTick,
+    ) -> impl Iterator<Item = (EntityRef, &T)> {
+        self.component_mgr
+            .changed_since::<T>(tick)
+            .map(|(id, c)| (self.entity_ref_of(id), c))
+    }
+
+    /// Returns the entities whose component `T` was removed after the given tick.
+    fn removed_since<T: Component>(&self, tick: ChangeTick) -> impl Iterator<Item = EntityRef> {
+        self.component_mgr
+            .removed_since::<T>(tick)
+            .map(|id| self.entity_ref_of(id))
+    }
+
     fn cloned_entity_manager(&self) -> EntityManager {
         self.entity_mgr.clone()
     }
 
+    fn cloned_rng(&self) -> WorldRng {
+        self.rng.clone()
+    }
+
     /// Updates the state through the given commands.
     fn apply_cmds(&mut self, mut cmds: StateCommands) {
+        // Stamp the modifications with a new tick.
+        self.change_tick += 1;
+        self.component_mgr.set_change_tick(self.change_tick);
         cmds.drain_modifications()
             .sorted_by_key(|m| m.0)
             .for_each(|m| m.1(self));
+        self.update_spatial_index(self.change_tick - 1);
         // Take in the emitted events.
         self.event_mgr.merge_events(cmds.tmp_event_mgr);
+        // Keep the random number generator advanced by the commands.
+        self.rng = cmds.rng;
     }
 
-    /// Clears all the events in the state. Should be called at the end of an update.
-    fn clear_events(&mut self) {
-        self.event_mgr.clear_all()
+    /// Starts a new frame of events, dropping the ones kept for long enough. Should be called at the end of an update.
+    fn advance_events(&mut self) {
+        self.event_mgr.advance_frame()
     }
 
     /// Converts the entities marked as invalid to eager entity removals and copies them into the given `StateCommands`.
@@ -143,13 +251,26 @@ impl StateReader for State {
     }
 }
 
+/// The phases in which the modifications are applied at the end of an update.
+/// Within a phase, the modifications are applied in the order they were dispatched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ModPhase {
+    Create,
+    Update,
+    RemoveComponent,
+    RemoveEntity,
+}
+
 /// Represents a state modification.
-struct StateMod(pub u8, pub Box<dyn FnOnce(&mut State)>);
+struct StateMod(pub ModPhase, pub Box<dyn FnOnce(&mut State) + Send>);
 
 pub struct StateCommands {
     tmp_entity_mgr: EntityManager,
     tmp_event_mgr: EventManager,
     modifications: Vec<StateMod>,
+    rng: WorldRng,
+    /// The name of the system that fills these commands in parallel with the others, which can't create entities.
+    parallel_system: Option<&'static str>,
 }
 
 impl<R: StateReader> From<&R> for StateCommands {
@@ -158,11 +279,23 @@ impl<R: StateReader> From<&R> for StateCommands {
             tmp_entity_mgr: state.cloned_entity_manager(),
             tmp_event_mgr: Default::default(),
             modifications: Default::default(),
+            rng: state.cloned_rng(),
+            parallel_system: None,
         }
     }
 }
 
 impl StateCommands {
+    /// Returns the random number generator of the world.
+    /// Its state is carried over to the world in the next update.
+    pub fn rng(&mut self) -> &mut WorldRng {
+        &mut self.rng
+    }
+
+    /// Reseeds the random number generator of the world.
+    pub fn reseed_rng(&mut self, seed: u64) {
+        self.rng = WorldRng::seeded(seed);
+    }
 
     /// Pushes a new event to be handled on the next update.
     pub fn emit_event<T: Event>(&mut self, evt: T) {
@@ -173,11 +306,18 @@ impl StateCommands {
 
     /// Dispatches a request to create a new entity in the next update and returns its would-be reference.
     /// Note that the reference will be invalid until the next update.
+    /// Panics if the commands are filled by a system running in parallel, which didn't declare that it creates entities in its opt-in.
     pub fn create_entity(&mut self) -> EntityRef {
+        if let Some(system_name) = self.parallel_system {
+            panic!(
+                "{} runs in parallel, so it cannot create entities without declaring it through ParallelOptIn::creates_entities",
+                system_name
+            );
+        }
         let f = Box::new(|state: &mut State| {
             state.entity_mgr.create();
         });
-        self.modifications.push(StateMod(0, f));
+        self.modifications.push(StateMod(ModPhase::Create, f));
         self.tmp_entity_mgr.create()
     }
 
@@ -187,7 +327,7 @@ impl StateCommands {
         let f = Box::new(move |state: &mut State| {
             state.push_bundle(bundle_clone);
         });
-        self.modifications.push(StateMod(0, f));
+        self.modifications.push(StateMod(ModPhase::Create, f));
         bundle
     }
 
@@ -202,7 +342,7 @@ impl StateCommands {
     pub fn update_component<T: Component>(
         &mut self,
         e: &EntityRef,
-        updater: impl FnOnce(&mut T) + 'static,
+        updater: impl FnOnce(&mut T) + Send + 'static,
     ) {
         let e = *e;
         let f = Box::new(move |state: &mut State| {
@@ -214,7 +354,30 @@ impl StateCommands {
                 updater(c);
             }
         });
-        self.modifications.push(StateMod(1, f));
+        self.modifications.push(StateMod(ModPhase::Update, f));
+    }
+
+    /// Dispatches a request to update a component on a particular entity using a closure,
+    /// inserting the default component first if the entity doesn't have one.
+    pub fn update_component_or_default<T: Component + Default>(
+        &mut self,
+        e: &EntityRef,
+        updater: impl FnOnce(&mut T) + Send + 'static,
+    ) {
+        let e = *e;
+        let f = Box::new(move |state: &mut State| {
+            if !state.is_valid(&e) {
+                return;
+            }
+            let components = state.component_mgr.get_components_mut::<T>();
+            if !components.has(e.id()) {
+                components.set(e.id(), T::default());
+            }
+            if let Some(c) = components.get_mut(e.id()) {
+                updater(c);
+            }
+        });
+        self.modifications.push(StateMod(ModPhase::Update, f));
     }
 
     /// Dispatches a request to remove the invalid references from a component.
@@ -229,7 +392,7 @@ impl StateCommands {
                 c.remove_invalids(&state.entity_mgr);
             }
         });
-        self.modifications.push(StateMod(1, f));
+        self.modifications.push(StateMod(ModPhase::Update, f));
     }
 
     /// Dispatches a request to set the component of the given entity in the next update.
@@ -242,7 +405,7 @@ impl StateCommands {
             let components = state.component_mgr.get_components_mut::<T>();
             components.set(e.id(), new_component);
         });
-        self.modifications.push(StateMod(1, f));
+        self.modifications.push(StateMod(ModPhase::Update, f));
     }
 
     /// Dispatches a request to set the components of the given entity with the given set of components in the next update.
@@ -254,7 +417,77 @@ impl StateCommands {
             }
             components.insert(e.id(), &mut state.component_mgr);
         });
-        self.modifications.push(StateMod(1, f));
+        self.modifications.push(StateMod(ModPhase::Update, f));
+    }
+
+    /// Dispatches a request to attach the given entity to a parent in the next update, detaching it from its previous parent.
+    /// The children are removed along with their parents. Ignored if the parent is a descendant of the child.
+    pub fn set_parent(&mut self, child: &EntityRef, parent: &EntityRef) {
+        let (child, parent) = (*child, *parent);
+        let f = Box::new(move |state: &mut State| {
+            if !state.is_valid(&child) || !state.is_valid(&parent) {
+                return;
+            }
+            // The requests forming a cycle are dropped, like the ones with invalid entities.
+            state.hierarchy.set_parent(child, parent).ok();
+        });
+        self.modifications.push(StateMod(ModPhase::Update, f));
+    }
+
+    /// Dispatches a request to detach the given entity from its parent in the next update.
+    pub fn remove_parent(&mut self, child: &EntityRef) {
+        let child = *child;
+        let f = Box::new(move |state: &mut State| {
+            state.hierarchy.remove_parent(&child);
+        });
+        self.modifications
+            .push(StateMod(ModPhase::RemoveComponent, f));
+    }
+
+    /// Dispatches a request to keep the components of type `T` in the given kind of storage from the next update on.
+    pub fn set_storage<T: Component>(&mut self, kind: StorageKind) {
+        let f = Box::new(move |state: &mut State| {
+            state.component_mgr.set_storage_kind::<T>(kind);
+        });
+        self.modifications.push(StateMod(ModPhase::Create, f));
+    }
+
+    /// Dispatches a request to keep the events of type `T` for the given number of frames from the next update on.
+    /// The events are kept for a single frame by default, which is enough for the systems.
+    pub fn set_event_retention<T: Event>(&mut self, frames: usize) {
+        let f = Box::new(move |state: &mut State| {
+            if let Some(events) = state.event_mgr.get_events_mut::<T>() {
+                events.set_retention(frames);
+            }
+        });
+        self.modifications.push(StateMod(ModPhase::Create, f));
+    }
+
+    /// Dispatches a request to set the resource of the given type in the next update.
+    pub fn set_resource<T: Resource>(&mut self, res: T) {
+        let f = Box::new(move |state: &mut State| {
+            state.resource_mgr.set(res);
+        });
+        self.modifications.push(StateMod(ModPhase::Update, f));
+    }
+
+    /// Dispatches a request to update the resource of the given type using a closure, if it exists.
+    pub fn update_resource<T: Resource>(&mut self, updater: impl FnOnce(&mut T) + Send + 'static) {
+        let f = Box::new(move |state: &mut State| {
+            if let Some(res) = state.resource_mgr.get_mut::<T>() {
+                updater(res);
+            }
+        });
+        self.modifications.push(StateMod(ModPhase::Update, f));
+    }
+
+    /// Dispatches a request to remove the resource of the given type in the next update.
+    pub fn remove_resource<T: Resource>(&mut self) {
+        let f = Box::new(move |state: &mut State| {
+            state.resource_mgr.remove::<T>();
+        });
+        self.modifications
+            .push(StateMod(ModPhase::RemoveComponent, f));
     }
 
     /// Dispatches a request to remove a component from the given entity in the `next next` update.
@@ -267,7 +500,8 @@ impl StateCommands {
             let components = state.component_mgr.get_components_mut::<T>();
             components.remove(e.id());
         });
-        self.modifications.push(StateMod(2, f));
+        self.modifications
+            .push(StateMod(ModPhase::RemoveComponent, f));
     }
 
     /// Dispatches a request to remove the given entity from the system in the next next update.
@@ -279,7 +513,41 @@ impl StateCommands {
             }
             state.mark_for_removal(&e);
         });
-        self.modifications.push(StateMod(3, f));
+        self.modifications.push(StateMod(ModPhase::RemoveEntity, f));
+    }
+
+    /// Creates empty commands that continue from these ones, so that they can be filled in parallel and joined back.
+    /// Each fork draws from its own random number generator, seeded by the generator of these commands.
+    pub(super) fn fork(&mut self) -> StateCommands {
+        StateCommands {
+            tmp_entity_mgr: self.tmp_entity_mgr.clone(),
+            tmp_event_mgr: Default::default(),
+            modifications: Default::default(),
+            rng: WorldRng::seeded(self.rng.next_u64()),
+            parallel_system: None,
+        }
+    }
+
+    /// Marks these commands as filled by the given system in parallel with the others, which forbids creating entities.
+    pub(super) fn run_in_parallel(&mut self, system_name: &'static str) {
+        self.parallel_system = Some(system_name);
+    }
+
+    /// Makes this fork hand out the would-be references after the entities created by the given fork.
+    pub(super) fn continue_entities_of(&mut self, prev_fork: &StateCommands) {
+        self.tmp_entity_mgr = prev_fork.tmp_entity_mgr.clone();
+    }
+
+    /// Appends the modifications & the events of the given forks in order.
+    /// The forks should create entities one after another, so the created entities are carried over from the one that created the most.
+    pub(super) fn join_all(&mut self, forks: impl IntoIterator<Item = StateCommands>) {
+        for fork in forks {
+            self.modifications.extend(fork.modifications);
+            self.tmp_event_mgr.merge_events(fork.tmp_event_mgr);
+            if fork.tmp_entity_mgr.num_alive() > self.tmp_entity_mgr.num_alive() {
+                self.tmp_entity_mgr = fork.tmp_entity_mgr;
+            }
+        }
     }
 
     /// Returns a draining iterator on the saved modifications.
@@ -295,9 +563,50 @@ impl StateCommands {
                 return;
             }
             state.bundles.remove(&e);
+            state.hierarchy.remove(&e);
             state.entity_mgr.remove(e.id());
             state.component_mgr.clear_components(e.id());
         });
-        self.modifications.push(StateMod(3, f));
+        self.modifications.push(StateMod(ModPhase::RemoveEntity, f));
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[test]
+    fn test_removal_order() {
+        // Removes half of the entities at once, and returns the ids that the new entities get.
+        let reused_ids = || {
+            let mut world = SystemManager::from(State::default());
+            let mut entities = vec![];
+            world.update_with(|_, cmds| {
+                entities = (0..20)
+                    .map(|_| cmds.create_from((Transform::default(),)))
+                    .collect();
+            });
+            world.update_with(|_, cmds| {
+                entities
+                    .iter()
+                    .step_by(2)
+                    .for_each(|e| cmds.mark_for_removal(e));
+            });
+            world.update_with_systems(Default::default());
+            let mut created = vec![];
+            world.update_with(|_, cmds| {
+                created = (0..10).map(|_| cmds.create_entity().id()).collect();
+            });
+            created
+        };
+        let ids = reused_ids();
+        assert_eq!(
+            ids.iter().copied().sorted().collect_vec(),
+            (0..20).step_by(2).collect_vec()
+        );
+        // The freed ids are handed out in the same order on every run.
+        assert!((0..5).all(|_| reused_ids() == ids));
     }
 }
@@ -0,0 +1,49 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::prelude::ChangeTick;
+
+type DerivedValue = Arc<dyn Any + Send + Sync>;
+
+/// Keeps the values derived from a state, along with the tick of the state they were derived at.
+/// The values are shared between the systems, which may run in parallel.
+#[derive(Default)]
+pub(super) struct DerivedCache(Mutex<HashMap<TypeId, (ChangeTick, DerivedValue)>>);
+
+impl DerivedCache {
+    /// Returns the cached value of type `T` if it was derived at the given tick, builds & caches it otherwise.
+    pub(super) fn get_or_build<T: Send + Sync + 'static>(
+        &self,
+        tick: ChangeTick,
+        build: impl FnOnce() -> T,
+    ) -> Arc<T> {
+        let cached = self
+            .0
+            .lock()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .filter(|(cached_tick, _)| *cached_tick == tick)
+            .map(|(_, value)| value.clone());
+        if let Some(value) = cached.and_then(|value| value.downcast::<T>().ok()) {
+            return value;
+        }
+        // Build without holding the lock, as the value might be derived from the other cached values.
+        let value = Arc::new(build());
+        self.0
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<T>(), (tick, value.clone()));
+        value
+    }
+}
+
+impl std::fmt::Debug for DerivedCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DerivedCache")
+            .field("len", &self.0.lock().map(|cache| cache.len()).unwrap_or(0))
+            .finish()
+    }
+}
@@ -0,0 +1,136 @@
+use notan::egui::epaint::ahash::HashMap;
+
+use crate::prelude::EntityRef;
+
+/// The parent/child relations between the entities.
+/// Each entity has at most one parent, and the children of an entity are kept in the order they were attached.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Hierarchy {
+    parents: HashMap<EntityRef, EntityRef>,
+    children: HashMap<EntityRef, Vec<EntityRef>>,
+}
+
+impl Hierarchy {
+    /// Returns the parent of the given entity, if it has one.
+    pub(super) fn parent_of(&self, e: &EntityRef) -> Option<&EntityRef> {
+        self.parents.get(e)
+    }
+
+    /// Returns the children of the given entity.
+    pub(super) fn children_of(&self, e: &EntityRef) -> &[EntityRef] {
+        self.children.get(e).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns true if `ancestor` is the given entity or one of its ancestors.
+    fn is_ancestor(&self, ancestor: &EntityRef, e: &EntityRef) -> bool {
+        let mut curr = Some(e);
+        while let Some(e) = curr {
+            if e == ancestor {
+                return true;
+            }
+            curr = self.parent_of(e);
+        }
+        false
+    }
+
+    /// Attaches the child to the given parent, detaching it from its previous parent.
+    /// Fails if the child is an ancestor of the parent, as that would form a cycle.
+    pub(super) fn set_parent(&mut self, child: EntityRef, parent: EntityRef) -> anyhow::Result<()> {
+        if self.is_ancestor(&child, &parent) {
+            return Err(anyhow::anyhow!(
+                "cannot attach {:?} to its descendant {:?}",
+                child,
+                parent
+            ));
+        }
+        self.remove_parent(&child);
+        self.parents.insert(child, parent);
+        self.children.entry(parent).or_default().push(child);
+        Ok(())
+    }
+
+    /// Detaches the given entity from its parent, if it has one.
+    pub(super) fn remove_parent(&mut self, child: &EntityRef) {
+        let Some(parent) = self.parents.remove(child) else {
+            return;
+        };
+        if let Some(siblings) = self.children.get_mut(&parent) {
+            siblings.retain(|sibling| sibling != child);
+            if siblings.is_empty() {
+                self.children.remove(&parent);
+            }
+        }
+    }
+
+    /// Drops all the relations of the given entity. Its children are left without a parent.
+    pub(super) fn remove(&mut self, e: &EntityRef) {
+        self.remove_parent(e);
+        for child in self.children.remove(e).into_iter().flatten() {
+            self.parents.remove(&child);
+        }
+    }
+
+    /// Returns the parents along with their children, sorted by the ids of the parents.
+    pub(super) fn to_sorted_vec(&self) -> Vec<(EntityRef, Vec<EntityRef>)> {
+        let mut children = Vec::from_iter(
+            self.children
+                .iter()
+                .map(|(parent, children)| (*parent, children.clone())),
+        );
+        children.sort_by_key(|(parent, _)| parent.id());
+        children
+    }
+
+    /// Reconstructs the hierarchy from the parents along with their children.
+    pub(super) fn from_vec(children: Vec<(EntityRef, Vec<EntityRef>)>) -> Self {
+        let parents = children
+            .iter()
+            .flat_map(|(parent, children)| children.iter().map(|child| (*child, *parent)))
+            .collect();
+        Self {
+            parents,
+            children: children.into_iter().collect(),
+        }
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[test]
+    fn test_hierarchy() {
+        let mut world = SystemManager::from(State::default());
+        let mut entities = vec![];
+        world.update_with(|_, cmds| {
+            entities = (0..4).map(|_| cmds.create_entity()).collect();
+            cmds.set_parent(&entities[1], &entities[0]);
+            cmds.set_parent(&entities[2], &entities[1]);
+            cmds.set_parent(&entities[3], &entities[1]);
+        });
+        let [root, mid, leaf1, leaf2] = entities[..] else {
+            unreachable!()
+        };
+        let state = world.get_state();
+        assert_eq!(state.parent_of(&leaf1), Some(&mid));
+        assert_eq!(state.children_of(&mid), &[leaf1, leaf2]);
+        // Reparenting, and ignoring the cycles.
+        world.update_with(|_, cmds| {
+            cmds.set_parent(&leaf2, &root);
+            cmds.set_parent(&root, &leaf1);
+        });
+        let state = world.get_state();
+        assert_eq!(state.children_of(&mid), &[leaf1]);
+        assert_eq!(state.children_of(&root), &[mid, leaf2]);
+        assert_eq!(state.parent_of(&root), None);
+        // Removing a subtree.
+        world.update_with(|_, cmds| cmds.mark_for_removal(&mid));
+        world.update_with_systems(UpdateContext::default());
+        let state = world.get_state();
+        assert!(!state.is_valid(&mid) && !state.is_valid(&leaf1));
+        assert!(state.is_valid(&root) && state.is_valid(&leaf2));
+        assert_eq!(state.children_of(&root), &[leaf2]);
+    }
+}
@@ -1,10 +1,13 @@
+use std::sync::Arc;
+
 use crate::prelude::{
-    component_tuple::ComponentTuple, EntityBundle, EntityManager, EntityRef, Event,
+    component_tuple::ComponentTuple, ChangeTick, Component, EntityBundle, EntityManager, EntityRef,
+    Event, EventSeq, Resource, SpatialIndex, WorldRng,
 };
 
 use super::StateCommands;
 
-pub trait StateReader: Default + 'static {
+pub trait StateReader: Default + Sync + 'static {
     type EventIterator<'a, T: Event>: Iterator<Item = &'a T>
     where
         Self: 'a;
@@ -19,6 +22,9 @@ pub trait StateReader: Default + 'static {
     fn will_be_removed(&self, e: &EntityRef) -> bool;
     /// Returns an iterator over the emitted events of the given type in the last frame.
     fn read_events<'a, T: Event>(&'a self) -> Self::EventIterator<'a, T>;
+    /// Returns an iterator over the kept events of the given type starting from the given sequence number,
+    /// along with the sequence number of the next event. See [`EventReader`](crate::prelude::EventReader).
+    fn read_events_since<T: Event>(&self, seq: EventSeq) -> (Self::EventIterator<'_, T>, EventSeq);
     /// Returns an iterator over the components identified by the given component selector.
     fn select<'a, S: ComponentTuple<'a>>(&'a self) -> Self::ComponentIterator<'a, S>;
     /// Returns the components of the given entity identified by the given component selector.
@@ -28,10 +34,36 @@ pub trait StateReader: Default + 'static {
     ) -> Option<<S as ComponentTuple<'a>>::RefOutput>;
     /// Reads a bundle of entities from the given `primary_entity`.
     fn read_bundle<'a, B: EntityBundle<'a>>(&'a self, primary_entity: &EntityRef) -> Option<B>;
+    /// Returns the parent of the given entity, if it has one.
+    fn parent_of(&self, e: &EntityRef) -> Option<&EntityRef>;
+    /// Returns the children of the given entity, in the order they were attached.
+    /// The members of a bundle are the children of its primary entity.
+    fn children_of(&self, e: &EntityRef) -> &[EntityRef];
+    /// Returns the index of the positions of the entities. See [`SpatialInsights`](crate::prelude::SpatialInsights) for the queries.
+    fn spatial_index(&self) -> &SpatialIndex;
+    /// Returns the resource of the given type, if it exists.
+    fn read_resource<T: Resource>(&self) -> Option<&T>;
+    /// Returns the value of type `T` derived from the state by the given function, building it at most once per applied update.
+    /// The value should only depend on the components, the resources & the hierarchy, and each type should be built by a single function.
+    fn derived<T: Send + Sync + 'static>(&self, build: impl FnOnce(&Self) -> T) -> Arc<T>;
+    /// Returns the tick of the last applied update.
+    fn change_tick(&self) -> ChangeTick;
+    /// Returns the entities whose component `T` was added after the given tick, along with the component.
+    fn added_since<T: Component>(&self, tick: ChangeTick) -> impl Iterator<Item = (EntityRef, &T)>;
+    /// Returns the entities whose component `T` was added or changed after the given tick, along with the component.
+    fn changed_since<T: Component>(
+        &self,
+        tick: ChangeTick,
+    ) -> impl Iterator<Item = (EntityRef, &T)>;
+    /// Returns the entities whose component `T` was removed after the given tick.
+    /// Note that the entities might have been removed altogether.
+    fn removed_since<T: Component>(&self, tick: ChangeTick) -> impl Iterator<Item = EntityRef>;
     fn cloned_entity_manager(&self) -> EntityManager;
+    /// Returns a copy of the random number generator of the world, to be advanced by the commands.
+    fn cloned_rng(&self) -> WorldRng;
     fn apply_cmds(&mut self, cmds: StateCommands);
-    /// Clears all the events in the state. Should be called at the end of an update.
-    fn clear_events(&mut self);
+    /// Starts a new frame of events, dropping the ones kept for long enough. Should be called at the end of an update.
+    fn advance_events(&mut self);
     /// Converts the entities marked as invalid to eager entity removals and copies them into the given `StateCommands`.
     fn transfer_removals(&mut self, cmds: &mut StateCommands);
     fn reset_removal_requests(&mut self);
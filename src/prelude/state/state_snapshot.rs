@@ -0,0 +1,327 @@
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::prelude::{
+    component::ComponentVec,
+    event::{Event, EventVec},
+    generic_bag::GenericBag,
+    resource::{GenericResource, Resource},
+    Component, EntityManager, EntityRef, WorldRng,
+};
+
+use super::{Hierarchy, State};
+
+type BagSerializer = fn(&dyn GenericBag) -> anyhow::Result<serde_json::Value>;
+type BagDeserializer = fn(serde_json::Value) -> anyhow::Result<Box<dyn GenericBag>>;
+type ResourceSerializer = fn(&dyn GenericResource) -> anyhow::Result<serde_json::Value>;
+type ResourceDeserializer = fn(serde_json::Value) -> anyhow::Result<Box<dyn GenericResource>>;
+
+/// The functions used to (de)serialize the component bag of a registered type.
+#[derive(Clone, Copy, Debug)]
+struct SnapshotHandler {
+    /// The stable name of the component type used in the snapshots.
+    name: &'static str,
+    serializer: BagSerializer,
+    deserializer: BagDeserializer,
+}
+
+/// The functions used to (de)serialize the kept events of a registered type.
+#[derive(Clone, Copy, Debug)]
+struct EventSnapshotHandler {
+    /// The stable name of the event type used in the snapshots.
+    name: &'static str,
+    serializer: BagSerializer,
+    deserializer: BagDeserializer,
+}
+
+/// The functions used to (de)serialize a registered resource type.
+#[derive(Clone, Copy, Debug)]
+struct ResourceSnapshotHandler {
+    /// The stable name of the resource type used in the snapshots.
+    name: &'static str,
+    serializer: ResourceSerializer,
+    deserializer: ResourceDeserializer,
+}
+
+/// Keeps track of the component, resource & event types that can be written into & read from a [`StateSnapshot`].
+/// Components & resources are stored type-erased, so every one of their types in the state must be registered before saving.
+/// Events are optional, the kept events of the unregistered types are dropped from the snapshots.
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotRegistry {
+    handlers: HashMap<TypeId, SnapshotHandler>,
+    type_ids: HashMap<&'static str, TypeId>,
+    resource_handlers: HashMap<TypeId, ResourceSnapshotHandler>,
+    resource_type_ids: HashMap<&'static str, TypeId>,
+    event_handlers: HashMap<TypeId, EventSnapshotHandler>,
+    event_type_ids: HashMap<&'static str, TypeId>,
+}
+
+impl SnapshotRegistry {
+    /// Registers the component type `T` to be saved & loaded with the snapshots.
+    pub fn register<T: Component + Serialize + DeserializeOwned>(&mut self) {
+        let handler = SnapshotHandler {
+            name: std::any::type_name::<T>(),
+            serializer: serialize_bag::<T>,
+            deserializer: deserialize_bag::<T>,
+        };
+        self.type_ids.insert(handler.name, TypeId::of::<T>());
+        self.handlers.insert(TypeId::of::<T>(), handler);
+    }
+
+    /// Registers the resource type `T` to be saved & loaded with the snapshots.
+    pub fn register_resource<T: Resource + Serialize + DeserializeOwned>(&mut self) {
+        let handler = ResourceSnapshotHandler {
+            name: std::any::type_name::<T>(),
+            serializer: serialize_resource::<T>,
+            deserializer: deserialize_resource::<T>,
+        };
+        self.resource_type_ids
+            .insert(handler.name, TypeId::of::<T>());
+        self.resource_handlers.insert(TypeId::of::<T>(), handler);
+    }
+
+    /// Registers the event type `T`, so that its kept events are saved & loaded with the snapshots, along with their retention.
+    pub fn register_event<T: Event + Serialize + DeserializeOwned>(&mut self) {
+        let handler = EventSnapshotHandler {
+            name: std::any::type_name::<T>(),
+            serializer: serialize_events::<T>,
+            deserializer: deserialize_events::<T>,
+        };
+        self.event_type_ids.insert(handler.name, TypeId::of::<T>());
+        self.event_handlers.insert(TypeId::of::<T>(), handler);
+    }
+}
+
+fn serialize_bag<T: Component + Serialize>(
+    bag: &dyn GenericBag,
+) -> anyhow::Result<serde_json::Value> {
+    let components = bag
+        .as_any()
+        .downcast_ref::<ComponentVec<T>>()
+        .ok_or(anyhow::anyhow!(
+            "could not downcast the generic bag to {:?}",
+            std::any::type_name::<ComponentVec<T>>()
+        ))?;
+    Ok(serde_json::to_value(components)?)
+}
+
+fn deserialize_bag<T: Component + DeserializeOwned>(
+    value: serde_json::Value,
+) -> anyhow::Result<Box<dyn GenericBag>> {
+    let components: ComponentVec<T> = serde_json::from_value(value)?;
+    Ok(Box::new(components))
+}
+
+/// The serialized form of the events of a type.
+#[derive(Serialize, Deserialize)]
+struct EventVecRepr<T> {
+    /// The number of frames the events are kept for.
+    retention: usize,
+    /// The events of the kept frames, oldest first.
+    frames: Vec<Vec<T>>,
+}
+
+fn serialize_events<T: Event + Serialize>(
+    bag: &dyn GenericBag,
+) -> anyhow::Result<serde_json::Value> {
+    let events = bag
+        .as_any()
+        .downcast_ref::<EventVec<T>>()
+        .ok_or(anyhow::anyhow!(
+            "could not downcast the generic bag to {:?}",
+            std::any::type_name::<EventVec<T>>()
+        ))?;
+    // Besides the last frame, which is yet to be handled by the systems, the older frames may still be read by the event readers.
+    Ok(serde_json::to_value(EventVecRepr {
+        retention: events.retention(),
+        frames: events.frames().map(Vec::from_iter).collect(),
+    })?)
+}
+
+fn deserialize_events<T: Event + DeserializeOwned>(
+    value: serde_json::Value,
+) -> anyhow::Result<Box<dyn GenericBag>> {
+    let repr = serde_json::from_value::<EventVecRepr<T>>(value)?;
+    let mut events = EventVec::<T>::default();
+    events.set_retention(repr.retention);
+    for (i, frame) in repr.frames.into_iter().enumerate() {
+        if i > 0 {
+            events.advance_frame();
+        }
+        frame.into_iter().for_each(|evt| events.push(evt));
+    }
+    Ok(Box::new(events))
+}
+
+fn serialize_resource<T: Resource + Serialize>(
+    res: &dyn GenericResource,
+) -> anyhow::Result<serde_json::Value> {
+    let res = res.as_any().downcast_ref::<T>().ok_or(anyhow::anyhow!(
+        "could not downcast the resource to {:?}",
+        std::any::type_name::<T>()
+    ))?;
+    Ok(serde_json::to_value(res)?)
+}
+
+fn deserialize_resource<T: Resource + DeserializeOwned>(
+    value: serde_json::Value,
+) -> anyhow::Result<Box<dyn GenericResource>> {
+    let res: T = serde_json::from_value(value)?;
+    Ok(Box::new(res))
+}
+
+/// A serializable copy of a [`State`].
+/// Only the kept events of the registered types are part of the snapshot, so snapshots should be taken in between the updates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    entity_mgr: EntityManager,
+    /// Component bags keyed by the registered component type names.
+    components: BTreeMap<String, serde_json::Value>,
+    to_remove: Vec<EntityRef>,
+    bundles: Vec<(EntityRef, Vec<EntityRef>)>,
+    /// The parents along with their children.
+    children: Vec<(EntityRef, Vec<EntityRef>)>,
+    /// Resources keyed by the registered resource type names.
+    resources: BTreeMap<String, serde_json::Value>,
+    /// The kept events along with their retention, keyed by the registered event type names.
+    #[serde(default)]
+    events: BTreeMap<String, serde_json::Value>,
+    rng: WorldRng,
+}
+
+impl State {
+    /// Takes a snapshot of the state. Fails if the state contains a component type missing from the registry.
+    pub fn to_snapshot(&self, registry: &SnapshotRegistry) -> anyhow::Result<StateSnapshot> {
+        let components = self
+            .component_mgr
+            .bags()
+            .map(|(type_id, bag)| {
+                let handler = registry.handlers.get(type_id).ok_or(anyhow::anyhow!(
+                    "component type {:?} is not registered for snapshots",
+                    bag.item_type_name()
+                ))?;
+                Ok((handler.name.to_string(), (handler.serializer)(bag)?))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let resources = self
+            .resource_mgr
+            .iter()
+            .map(|(type_id, res)| {
+                let handler = registry
+                    .resource_handlers
+                    .get(type_id)
+                    .ok_or(anyhow::anyhow!(
+                        "resource type {:?} is not registered for snapshots",
+                        res.type_name()
+                    ))?;
+                Ok((handler.name.to_string(), (handler.serializer)(res)?))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let events = self
+            .event_mgr
+            .bags()
+            .filter_map(|(type_id, bag)| registry.event_handlers.get(type_id).map(|h| (h, bag)))
+            .map(|(handler, bag)| Ok((handler.name.to_string(), (handler.serializer)(bag)?)))
+            .collect::<anyhow::Result<_>>()?;
+        let to_remove = Vec::from_iter(self.to_remove.iter().cloned());
+        let mut bundles = Vec::from_iter(
+            self.bundles
+                .iter()
+                .map(|(primary, entities)| (*primary, entities.clone())),
+        );
+        bundles.sort_by_key(|(primary, _)| primary.id());
+        Ok(StateSnapshot {
+            entity_mgr: self.entity_mgr.clone(),
+            components,
+            to_remove,
+            bundles,
+            children: self.hierarchy.to_sorted_vec(),
+            resources,
+            events,
+            rng: self.rng.clone(),
+        })
+    }
+
+    /// Reconstructs a state from the given snapshot.
+    pub fn from_snapshot(
+        snapshot: StateSnapshot,
+        registry: &SnapshotRegistry,
+    ) -> anyhow::Result<Self> {
+        let mut state = State {
+            entity_mgr: snapshot.entity_mgr,
+            to_remove: snapshot.to_remove.into_iter().collect(),
+            bundles: snapshot.bundles.into_iter().collect(),
+            hierarchy: Hierarchy::from_vec(snapshot.children),
+            rng: snapshot.rng,
+            // The loaded components are stamped with the first tick.
+            change_tick: 1,
+            ..Default::default()
+        };
+        state.component_mgr.set_change_tick(state.change_tick);
+        for (name, value) in snapshot.components {
+            let type_id = registry.type_ids.get(name.as_str()).ok_or(anyhow::anyhow!(
+                "component type {:?} is not registered for snapshots",
+                name
+            ))?;
+            let handler = &registry.handlers[type_id];
+            state
+                .component_mgr
+                .insert_bag(*type_id, (handler.deserializer)(value)?);
+        }
+        for (name, value) in snapshot.resources {
+            let type_id = registry
+                .resource_type_ids
+                .get(name.as_str())
+                .ok_or(anyhow::anyhow!(
+                    "resource type {:?} is not registered for snapshots",
+                    name
+                ))?;
+            let handler = &registry.resource_handlers[type_id];
+            state
+                .resource_mgr
+                .insert_boxed(*type_id, (handler.deserializer)(value)?);
+        }
+        for (name, value) in snapshot.events {
+            let type_id = registry
+                .event_type_ids
+                .get(name.as_str())
+                .ok_or(anyhow::anyhow!(
+                    "event type {:?} is not registered for snapshots",
+                    name
+                ))?;
+            let handler = &registry.event_handlers[type_id];
+            state
+                .event_mgr
+                .insert_bag(*type_id, (handler.deserializer)(value)?);
+        }
+        state.update_spatial_index(0);
+        Ok(state)
+    }
+
+    /// Saves a snapshot of the state into the file at the given path.
+    pub fn save_to_file(
+        &self,
+        path: impl AsRef<Path>,
+        registry: &SnapshotRegistry,
+    ) -> anyhow::Result<()> {
+        let snapshot = self.to_snapshot(registry)?;
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(file, &snapshot)?;
+        Ok(())
+    }
+
+    /// Loads the state from the snapshot file at the given path.
+    pub fn load_from_file(
+        path: impl AsRef<Path>,
+        registry: &SnapshotRegistry,
+    ) -> anyhow::Result<Self> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let snapshot: StateSnapshot = serde_json::from_reader(file)?;
+        Self::from_snapshot(snapshot, registry)
+    }
+}
@@ -1,6 +1,6 @@
 use crate::prelude::*;
 
-use super::Transform;
+use super::{component_tuple::ComponentTuple, Transform};
 
 /// Provides insights about the given state of the system. Other modules should extend this with new functionality.
 pub struct StateInsights<'a, R: StateReader>(pub &'a R);
@@ -19,9 +19,8 @@ pub trait AnchoredInsights<'a> {
 impl<'a, R: StateReader> AnchoredInsights<'a> for StateInsights<'a, R> {
     /// Returns the anchor parent if it exists.
     fn anchor_parent_of(&self, e: &EntityRef) -> Option<&'a EntityRef> {
-        self.0
-            .select_one::<(AnchorTransform,)>(e)
-            .map(|(anchor,)| &anchor.0)
+        self.0.select_one::<(AnchorTransform,)>(e)?;
+        self.0.parent_of(e)
     }
 }
 
@@ -53,3 +52,64 @@ impl<'a, R: StateReader> TransformInsights<'a> for StateInsights<'a, R> {
         Some((t1.x - t2.x, t1.y - t2.y))
     }
 }
+
+/// Provides insights about the entities around a position, through the spatial index of the state.
+pub trait SpatialInsights<'a> {
+    /// Returns the entities within the given distance of the center.
+    fn within_radius(
+        &self,
+        center: (f32, f32),
+        radius: f32,
+    ) -> impl Iterator<Item = EntityRef> + 'a;
+    /// Returns the entities within the given rectangle.
+    fn within_rect(
+        &self,
+        top_left: (f32, f32),
+        bottom_right: (f32, f32),
+    ) -> impl Iterator<Item = EntityRef> + 'a;
+    /// Returns up to `k` entities with the components `S` that are accepted by the filter, the nearest to the center first.
+    fn k_nearest<S: ComponentTuple<'a>>(
+        &self,
+        center: (f32, f32),
+        k: usize,
+        filter: impl Fn(&EntityRef) -> bool,
+    ) -> Vec<EntityRef>;
+    /// Returns the nearest entity to the center with the components `S` that is accepted by the filter.
+    fn nearest<S: ComponentTuple<'a>>(
+        &self,
+        center: (f32, f32),
+        filter: impl Fn(&EntityRef) -> bool,
+    ) -> Option<EntityRef> {
+        self.k_nearest::<S>(center, 1, filter).pop()
+    }
+}
+
+impl<'a, R: StateReader> SpatialInsights<'a> for StateInsights<'a, R> {
+    fn within_radius(
+        &self,
+        center: (f32, f32),
+        radius: f32,
+    ) -> impl Iterator<Item = EntityRef> + 'a {
+        self.0.spatial_index().within_radius(center, radius)
+    }
+
+    fn within_rect(
+        &self,
+        top_left: (f32, f32),
+        bottom_right: (f32, f32),
+    ) -> impl Iterator<Item = EntityRef> + 'a {
+        self.0.spatial_index().within_rect(top_left, bottom_right)
+    }
+
+    fn k_nearest<S: ComponentTuple<'a>>(
+        &self,
+        center: (f32, f32),
+        k: usize,
+        filter: impl Fn(&EntityRef) -> bool,
+    ) -> Vec<EntityRef> {
+        let state = self.0;
+        state.spatial_index().k_nearest(center, k, |e| {
+            state.select_one::<S>(e).is_some() && filter(e)
+        })
+    }
+}
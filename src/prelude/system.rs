@@ -1,7 +1,15 @@
-use super::{StateCommands, StateReader};
+use serde::{Deserialize, Serialize};
+
+use super::{ChangeTick, StateCommands, StateReader};
+
+mod parallel_opt_in;
+mod system_schedule;
+
+pub use parallel_opt_in::*;
+pub use system_schedule::*;
 
 /// Represents the current state of the controller.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct ControlMap {
     pub left_is_down: bool,
     pub right_is_down: bool,
@@ -15,6 +23,8 @@ pub struct ControlMap {
     pub mouse_right_was_released: bool,
     pub mouse_left_is_down: bool,
     pub mouse_right_is_down: bool,
+    #[serde(default)]
+    pub reload_was_pressed: bool,
     pub mouse_pos: (f32, f32),
 }
 
@@ -34,18 +44,30 @@ impl ControlMap {
             mouse_right_was_released: app.mouse.right_was_released(),
             mouse_left_is_down: app.mouse.left_is_down(),
             mouse_right_is_down: app.mouse.right_is_down(),
+            reload_was_pressed: app.keyboard.was_pressed(notan::prelude::KeyCode::R),
         }
     }
 }
 
 /// Contains the context information for an update iteration.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
 pub struct UpdateContext {
     pub dt: f32,
     pub control_map: ControlMap,
+    /// The change tick of the state when the updated system last ran, or zero if it has never run.
+    /// Set separately for each system by the system manager.
+    #[serde(skip)]
+    pub last_run: ChangeTick,
 }
 
-pub trait System<R: StateReader>: 'static + std::fmt::Debug {
+pub trait System<R: StateReader>: 'static + std::fmt::Debug + Send {
     /// The update function for the system. This is called at every update iteration on the registered systems.
     fn update(&mut self, ctx: &UpdateContext, state: &R, cmds: &mut StateCommands);
+
+    /// Opts the system in to run in parallel with the other opted-in systems.
+    /// Systems that don't opt in, or that declare creating entities, run one after another on a single thread,
+    /// alongside the parallel ones.
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        None
+    }
 }
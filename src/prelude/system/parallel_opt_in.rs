@@ -0,0 +1,23 @@
+/// Opts a system in to run in parallel with the other opted-in systems.
+///
+/// This is not a declaration of the components or events the system accesses. All the systems read the state as it was at the beginning
+/// of the update, and their commands are applied in the order of the schedule, so their accesses never conflict.
+/// Only the creation of entities has to be declared. The systems that create entities still run one after another,
+/// as the would-be references of the new entities depend on the entities created before them.
+#[derive(Clone, Debug, Default)]
+pub struct ParallelOptIn {
+    creates_entities: bool,
+}
+
+impl ParallelOptIn {
+    /// Declares that the system creates entities through its commands.
+    pub fn creates_entities(mut self) -> Self {
+        self.creates_entities = true;
+        self
+    }
+
+    /// Returns true if the system is declared to create entities.
+    pub fn does_create_entities(&self) -> bool {
+        self.creates_entities
+    }
+}
@@ -0,0 +1,246 @@
+use std::{any::TypeId, collections::BTreeSet};
+
+use crate::prelude::{ChangeTick, StateReader};
+
+use super::{ParallelOptIn, System};
+
+/// The stages of an update, in the order their systems are scheduled.
+/// The commands are applied phase by phase (creations, updates, then removals), and in the order of the schedule within a phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// Bookkeeping that the rest of the update relies on, such as advancing the world time.
+    PreUpdate,
+    /// The simulation itself: control, movement, interactions & physics.
+    #[default]
+    Update,
+    /// Reactions to the simulation, such as expiring timers & applying effects.
+    PostUpdate,
+    /// Preparing the state to be drawn.
+    RenderPrep,
+}
+
+/// A registered system along with its scheduling constraints.
+#[derive(Debug)]
+pub struct SystemEntry<R: StateReader> {
+    pub(in crate::prelude) system: Box<dyn System<R>>,
+    type_id: TypeId,
+    name: &'static str,
+    parallel_opt_in: Option<ParallelOptIn>,
+    stage: Stage,
+    /// The types of the systems that should run before this one.
+    after: Vec<TypeId>,
+    /// The types of the systems that should run after this one.
+    before: Vec<TypeId>,
+    /// The change tick of the state when the system last ran.
+    pub(in crate::prelude) last_run: ChangeTick,
+}
+
+impl<R: StateReader> SystemEntry<R> {
+    pub(in crate::prelude) fn new<S: System<R>>(system: S) -> Self {
+        Self {
+            type_id: TypeId::of::<S>(),
+            name: std::any::type_name::<S>(),
+            parallel_opt_in: system.parallel_opt_in(),
+            system: Box::new(system),
+            stage: Stage::default(),
+            after: Default::default(),
+            before: Default::default(),
+            last_run: 0,
+        }
+    }
+
+    /// Returns the type name of the system.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the opt-in of the system to run in parallel, if any.
+    pub fn parallel_opt_in(&self) -> Option<&ParallelOptIn> {
+        self.parallel_opt_in.as_ref()
+    }
+
+    /// Moves the system into the given stage. Systems are in [`Stage::Update`] by default.
+    pub fn in_stage(&mut self, stage: Stage) -> &mut Self {
+        self.stage = stage;
+        self
+    }
+
+    /// Makes the system run after the systems of type `S` in the same stage.
+    /// Fails the scheduling if a system of type `S` is in a later stage.
+    pub fn after<S: System<R>>(&mut self) -> &mut Self {
+        self.after.push(TypeId::of::<S>());
+        self
+    }
+
+    /// Makes the system run before the systems of type `S` in the same stage.
+    /// Fails the scheduling if a system of type `S` is in an earlier stage.
+    pub fn before<S: System<R>>(&mut self) -> &mut Self {
+        self.before.push(TypeId::of::<S>());
+        self
+    }
+
+    /// Returns true if this system is constrained to run before the given one.
+    fn precedes(&self, other: &Self) -> bool {
+        other.after.contains(&self.type_id) || self.before.contains(&other.type_id)
+    }
+
+    /// Returns true if the system can run in parallel with the others.
+    pub fn runs_in_parallel(&self) -> bool {
+        self.parallel_opt_in
+            .as_ref()
+            .map(|opt_in| !opt_in.does_create_entities())
+            .unwrap_or(false)
+    }
+}
+
+/// Computes the order in which the given systems should run.
+/// Systems run stage by stage, respecting the before/after constraints within the stages and the registration order otherwise.
+pub(in crate::prelude) fn compute_schedule<R: StateReader>(
+    systems: &[SystemEntry<R>],
+) -> anyhow::Result<Vec<usize>> {
+    let mut successors = vec![Vec::new(); systems.len()];
+    let mut num_predecessors = vec![0; systems.len()];
+    for (i, first) in systems.iter().enumerate() {
+        for (j, second) in systems.iter().enumerate() {
+            if i == j || !first.precedes(second) {
+                continue;
+            }
+            if first.stage > second.stage {
+                return Err(anyhow::anyhow!(
+                    "{} should run before {}, but it is in a later stage",
+                    first.name,
+                    second.name
+                ));
+            }
+            // Constraints across the stages are already satisfied by the stage order.
+            if first.stage == second.stage {
+                successors[i].push(j);
+                num_predecessors[j] += 1;
+            }
+        }
+    }
+    // Kahn's algorithm, picking the earliest registered system among the ready ones.
+    let mut ready = systems
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| num_predecessors[*i] == 0)
+        .map(|(i, entry)| (entry.stage, i))
+        .collect::<BTreeSet<_>>();
+    let mut schedule = Vec::with_capacity(systems.len());
+    while let Some((stage, i)) = ready.pop_first() {
+        schedule.push(i);
+        for &j in &successors[i] {
+            num_predecessors[j] -= 1;
+            if num_predecessors[j] == 0 {
+                ready.insert((stage, j));
+            }
+        }
+    }
+    if schedule.len() < systems.len() {
+        let cyclic = systems
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| num_predecessors[*i] > 0)
+            .map(|(_, entry)| entry.name)
+            .collect::<Vec<_>>();
+        return Err(anyhow::anyhow!(
+            "cyclic dependency between the systems {:?}",
+            cyclic
+        ));
+    }
+    Ok(schedule)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::prelude::*;
+
+    #[derive(Debug)]
+    struct First;
+    #[derive(Debug)]
+    struct Second;
+    #[derive(Debug)]
+    struct Third;
+
+    macro_rules! impl_noop_system {
+        ($($name:ident),*) => {
+            $(impl System<State> for $name {
+                fn update(&mut self, _: &UpdateContext, _: &State, _: &mut StateCommands) {}
+            })*
+        };
+    }
+    impl_noop_system!(First, Second, Third);
+
+    #[test]
+    fn test_system_schedule() {
+        let mut world = SystemManager::from(State::default());
+        world.register_system(First).in_stage(Stage::PostUpdate);
+        world.register_system(Second).after::<Third>();
+        world.register_system(Third);
+        assert_eq!(
+            world
+                .scheduled_systems()
+                .unwrap()
+                .into_iter()
+                .map(SystemEntry::name)
+                .collect::<Vec<_>>(),
+            vec![
+                std::any::type_name::<Third>(),
+                std::any::type_name::<Second>(),
+                std::any::type_name::<First>(),
+            ]
+        );
+        // Contradicting the stages.
+        world.register_system(Third).after::<First>();
+        assert!(world.build_schedule().is_err());
+        // Cyclic dependencies.
+        let mut world = SystemManager::from(State::default());
+        world.register_system(First).after::<Third>();
+        world.register_system(Second).after::<First>();
+        world.register_system(Third).after::<Second>();
+        assert!(world.build_schedule().is_err());
+        // The last valid schedule keeps running when a later registration contradicts it.
+        let mut world = SystemManager::from(State::default());
+        world.register_system(Creator(None));
+        world.update_with_systems(Default::default());
+        world.register_system(First).after::<Second>();
+        world.register_system(Second).after::<First>();
+        world.update_with_systems(Default::default());
+        assert_eq!(world.get_state().cloned_entity_manager().num_alive(), 2);
+        assert!(world.scheduled_systems().is_err());
+    }
+
+    /// Creates an entity in every update, with the given opt-in to run in parallel.
+    #[derive(Debug)]
+    struct Creator(Option<ParallelOptIn>);
+
+    impl System<State> for Creator {
+        fn update(&mut self, _: &UpdateContext, _: &State, cmds: &mut StateCommands) {
+            cmds.create_entity();
+        }
+
+        fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_parallel_entity_creation() {
+        // Creating entities is fine when declared, or when the system doesn't opt in to run in parallel.
+        let mut world = SystemManager::from(State::default());
+        world.register_system(Creator(None));
+        world.register_system(Creator(Some(ParallelOptIn::default().creates_entities())));
+        world.update_with_systems(Default::default());
+        assert_eq!(world.get_state().cloned_entity_manager().num_alive(), 2);
+        // Creating entities in parallel fails right away, even when the systems run serially.
+        let mut world = SystemManager::from(State::default());
+        world.register_system(Creator(Some(ParallelOptIn::default())));
+        world.set_serial(true);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.update_with_systems(Default::default())
+        }));
+        assert!(result.is_err());
+    }
+}
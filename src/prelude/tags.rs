@@ -5,7 +5,7 @@ use crate::prelude::*;
 /// Represents a named struct that can output a set of tags for a given entity.
 /// Alternatively, we can call this a `representation` of an entity.
 /// For example, an idle character with opened backpack may have the tags "opened" as a `Storage` and "idle" as a `Character`.
-pub trait TagSource: 'static + Clone + std::fmt::Debug {
+pub trait TagSource: 'static + Clone + std::fmt::Debug + Send + Sync {
     /// The types of the outputted tags.
     type TagType: 'static + Clone + std::fmt::Debug + std::hash::Hash + Into<&'static str>;
     /// Returns the name of this tag source.
@@ -1,9 +1,11 @@
 use std::marker::PhantomData;
 
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
 /// A wrapper compoonent that replaces itself with the inner component after a certain time.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimedAdd<T: Component> {
     remaining: f32,
     component: T,
@@ -48,10 +50,14 @@ impl<T: Component, R: StateReader> System<R> for TimedAddSystem<T> {
                 }
             });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
 
 /// A wrapper compoonent that removes itself and the component `T` after a certain time.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimedRemove<T: Component> {
     remaining: f32,
     pd: PhantomData<T>,
@@ -96,10 +102,14 @@ impl<T: Component, R: StateReader> System<R> for TimedRemoveSystem<T> {
                 }
             });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
 
 /// A wrapper compoonent that emits an event and removes itself after given time.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimedEmit<T: Event> {
     remaining: f32,
     event: T,
@@ -142,4 +152,8 @@ impl<T: Component, R: StateReader> System<R> for TimedEmitSystem<T> {
                 }
             });
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
@@ -0,0 +1,129 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{item::ItemTransferReq, prelude::*, world_gen::WorldGenerator};
+
+/// Everything that is fed into the world from the outside during a single frame.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FrameInput {
+    pub ctx: UpdateContext,
+    /// The item transfers requested through the ui since the last frame.
+    pub item_transfers: Vec<ItemTransferReq>,
+}
+
+impl FrameInput {
+    /// Steps the given world by a single frame using this input.
+    pub fn apply(self, world: &mut SystemManager<State>) {
+        world.update_with(|_, cmds| {
+            self.item_transfers
+                .into_iter()
+                .for_each(|req| cmds.emit_event(req));
+        });
+        world.update_with_systems(self.ctx);
+    }
+}
+
+/// Records a session into a file.
+/// The first line contains the snapshot of the world at the start, and each following line contains the [`FrameInput`] of a frame.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Creates a recording file at the given path, starting from the current state of the world.
+    pub fn create(path: impl AsRef<Path>, world: &SystemManager<State>) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &WorldGenerator::snapshot(world)?)?;
+        writer.write_all(b"\n")?;
+        Ok(Self { writer })
+    }
+
+    /// Appends the input of a frame to the recording.
+    /// The file is flushed every frame, so that the recording survives a crash.
+    pub fn record(&mut self, input: &FrameInput) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.writer, input)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Plays back a session recorded by a [`Recorder`].
+#[derive(Clone, Debug, Default)]
+pub struct Replay {
+    inputs: VecDeque<FrameInput>,
+}
+
+impl Replay {
+    /// Loads the recording at the given path.
+    /// Returns the world at the start of the recording along with the replay of its inputs.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<(SystemManager<State>, Self)> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines
+            .next()
+            .ok_or(anyhow::anyhow!("the recording is empty"))??;
+        let world = WorldGenerator::restore(serde_json::from_str(&header)?)?;
+        let inputs = lines
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<anyhow::Result<_>>()?;
+        Ok((world, Self { inputs }))
+    }
+
+    /// Returns the input of the next frame, or `None` if the replay is over.
+    pub fn next_input(&mut self) -> Option<FrameInput> {
+        self.inputs.pop_front()
+    }
+
+    /// Returns the number of frames left to replay.
+    pub fn remaining(&self) -> usize {
+        self.inputs.len()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::{
+        character::Player,
+        headless::{HeadlessRunner, InputScript},
+        physics::ProjectileGenerator,
+    };
+
+    #[test]
+    fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("replay_test_{}.jsonl", std::process::id()));
+        let dump = |runner: &HeadlessRunner| {
+            serde_json::to_string(&WorldGenerator::snapshot(runner.world()).unwrap()).unwrap()
+        };
+        // Record a session right after the world is generated, while the generated items are yet to be transferred.
+        // The player picks up the machine gun, so that the spread of its shots is replayed too.
+        let mut world = WorldGenerator::generate_debug_world(7);
+        world.update_with(|state, cmds| {
+            let player = state.read_resource::<Player>().unwrap().0;
+            let (gun, _) = state.select::<(ProjectileGenerator,)>().next().unwrap();
+            cmds.emit_event(ItemTransferReq::equip_from_ground(gun, player));
+        });
+        let recorder = Recorder::create(&path, &world).unwrap();
+        let mut live = HeadlessRunner::new(world, InputScript::Wander).with_recorder(recorder);
+        let mut fired = false;
+        for _ in 0..200 {
+            live.step().unwrap();
+            fired |= live.stats().projectiles > 0;
+        }
+        assert!(fired);
+        // The replay ends up in the same state as the recorded session.
+        let (world, replay) = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.remaining(), 200);
+        let mut replayed = HeadlessRunner::new(world, InputScript::Idle).with_replay(replay);
+        replayed.run(200).unwrap();
+        assert_eq!(dump(&live), dump(&replayed));
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{building::Building, character::Character, item::Item, prelude::*, vehicle::Vehicle};
 
 mod default_sprite;
@@ -12,7 +14,7 @@ use sprite_asset_parser::*;
 use sprite_frames::*;
 use sprite_tags::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct TilingConfig {
     pub repeat_x: u8,
     pub repeat_y: u8,
@@ -27,7 +29,7 @@ impl Default for TilingConfig {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Sprite {
     pub sprite_id: String,
     pub z_index: usize,
@@ -133,4 +135,8 @@ impl<R: StateReader> System<R> for SpriteAnimationSystem {
             });
         })
     }
+
+    fn parallel_opt_in(&self) -> Option<ParallelOptIn> {
+        Some(ParallelOptIn::default())
+    }
 }
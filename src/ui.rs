@@ -4,7 +4,7 @@ use itertools::Itertools;
 use notan::egui;
 
 use crate::{
-    character::CharacterBundle,
+    character::{CharacterBundle, Player},
     item::{Equipment, ItemTransferReq, Storage},
     prelude::*,
 };
@@ -34,7 +34,7 @@ impl<'a, R: StateReader> UiBuilder<'a, R> {
     }
 
     fn build(&mut self, game_state: &'a R) -> HashMap<egui::Id, WindowType> {
-        let player_entity = EntityRef::new(0, 0);
+        let player_entity = game_state.read_resource::<Player>().unwrap().0;
         let player_char = game_state
             .read_bundle::<CharacterBundle>(&player_entity)
             .unwrap();
@@ -101,13 +101,14 @@ impl<'a, R: StateReader> UiBuilder<'a, R> {
     }
 }
 
+/// Draws the ui and returns the item transfers requested through it.
 pub fn draw_ui<R: StateReader>(
     ctx: &egui::Context,
     game_state: &R,
-    ui_cmds: &mut StateCommands,
     ui_state: &mut UiState,
-) {
+) -> Vec<ItemTransferReq> {
     let window_types = UiBuilder::<R>::default().build_and_draw(ctx, game_state, ui_state);
+    let mut item_transfer_reqs = Vec::new();
     if let Some(drag_result) = ui_state.item_drag.try_complete(ctx) {
         let from_win_type = drag_result
             .from_win_id
@@ -126,7 +127,8 @@ pub fn draw_ui<R: StateReader>(
                     from_loc: from_win_type.into(),
                     to_loc: to_win_type.into(),
                 };
-                ui_cmds.emit_event(item_transfer_req);
+                item_transfer_reqs.push(item_transfer_req);
             });
     }
+    item_transfer_reqs
 }
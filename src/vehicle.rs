@@ -9,5 +9,7 @@ pub use vehicle_insights::*;
 pub use vehicle_interaction::*;
 pub use vehicle_system::*;
 
-#[derive(Clone, Copy, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Vehicle;
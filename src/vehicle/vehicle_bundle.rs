@@ -20,7 +20,11 @@ impl VehicleBundle {
             MaxSpeed(1000.),
             Storage::new(6),
             InteractTarget::<Storage>::default(),
-            Hitbox(HitboxType::Dynamic, Shape::Rect { w: 100., h: 40. }),
+            Hitbox(
+                HitboxType::Dynamic,
+                Shape::Rect { w: 100., h: 40. },
+                CollisionFilter::new(Layers::VEHICLES, Layers::ALL),
+            ),
             InteractTarget::<Hitbox>::default(),
             InteractTarget::<VisionField>::default(),
         ));
@@ -31,16 +35,23 @@ impl VehicleBundle {
                 Sprite::new("basic_car", 3),
                 ProximityInteractable,
                 TargetRotation::default(),
+                RigidBody::new(1000., 0.1, 0.5),
+                // The engine.
+                MovementNoise::new(1200., 0.5),
                 Equipment::new([EquipmentSlot::VehicleGas, EquipmentSlot::VehicleModule]),
                 InteractTarget::<Equipment>::default(),
             ),
         );
         let door = cmds.create_from((
             Transform::default(),
-            AnchorTransform(vehicle, (0., -40.), 0.),
+            AnchorTransform((0., -40.), 0.),
             ProximityInteractable,
             UntargetedInteractionDelegate(vehicle),
-            Hitbox(HitboxType::Ghost, Shape::Rect { w: 40., h: 30. }),
+            Hitbox(
+                HitboxType::Ghost,
+                Shape::Rect { w: 40., h: 30. },
+                CollisionFilter::new(Layers::SENSORS, Layers::CHARACTERS),
+            ),
             InteractTarget::<Hitbox>::default(),
         ));
         cmds.push_bundle(Self { vehicle, door })
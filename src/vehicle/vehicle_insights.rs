@@ -23,9 +23,6 @@ impl<'a, R: StateReader> VehicleInsights<'a> for StateInsights<'a, R> {
     }
 
     fn vehicle_of(&self, actor: &EntityRef) -> Option<EntityRef> {
-        self.0
-            .select::<(InteractTarget<Vehicle>,)>()
-            .find(|(_, (vehicle_intr,))| vehicle_intr.actors.contains(actor))
-            .map(|(e, _)| e)
+        self.interactions_of::<Vehicle>(actor).next()
     }
 }
@@ -19,7 +19,8 @@ impl<R: StateReader> System<R> for VehicleSystem {
                 // Copy the driver's controllers to the vehicle.
                 cmds.emit_event(CopyControllersReq::new(*driver, *vehicle));
                 // Anchor the driver to the vehicle.
-                cmds.set_component(driver, AnchorTransform(*vehicle, (0., 0.), 0.));
+                cmds.set_component(driver, AnchorTransform((0., 0.), 0.));
+                cmds.set_parent(driver, vehicle);
                 if let Some((vehicle_transform,)) = state.select_one::<(Transform,)>(vehicle) {
                     cmds.set_component(driver, vehicle_transform.clone());
                 }
@@ -40,6 +41,7 @@ impl<R: StateReader> System<R> for VehicleSystem {
                 cmds.set_component(vehicle, TargetVelocity { x: 0., y: 0. });
                 // Remove the driver's anchor.
                 cmds.remove_component::<AnchorTransform>(driver);
+                cmds.remove_parent(driver);
                 // Update driver's hitbox to dynamic.
                 cmds.update_component(driver, |hb: &mut Hitbox| {
                     hb.0 = HitboxType::Dynamic;
@@ -1,25 +1,36 @@
+use std::path::Path;
+
 use crate::prelude::*;
 
 mod empty_world;
 mod entity_template;
 mod environment_generator;
 mod generation_area;
+mod world_snapshot;
 
 use empty_world::*;
 pub use entity_template::*;
 pub use environment_generator::*;
 pub use generation_area::*;
+pub use world_snapshot::*;
 
 pub struct WorldTemplate {
     entity_templates: Vec<(Transform, EntityTemplate)>,
+    /// The seed of the random number generator of the world.
+    seed: u64,
 }
 
 impl WorldTemplate {
     pub fn new(entity_templates: impl IntoIterator<Item = (Transform, EntityTemplate)>) -> Self {
         Self {
             entity_templates: entity_templates.into_iter().collect(),
+            seed: 0,
         }
     }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
 }
 
 pub struct WorldGenerator;
@@ -28,6 +39,7 @@ impl WorldGenerator {
     pub fn generate(world_template: WorldTemplate) -> SystemManager<State> {
         let mut world = create_empty_world();
         world.update_with(|_, cmds| {
+            cmds.reseed_rng(world_template.seed);
             world_template
                 .entity_templates
                 .into_iter()
@@ -37,4 +49,63 @@ impl WorldGenerator {
         });
         world
     }
+
+    /// Generates the world used while developing the game.
+    pub fn generate_debug_world(seed: u64) -> SystemManager<State> {
+        let mut world = Self::generate(
+            WorldTemplate::new([
+                (Transform::at(-40., -40.), PLAYER_TEMPLATE),
+                (Transform::at(50., 50.), CHEST_TEMPLATE),
+                (Transform::at(500., 500.), BASIC_CAR_TEMPLATE),
+                // (Transform::at(10., 10.), HAND_GUN_TEMPLATE),
+                (Transform::at(10., 10.), MACHINE_GUN_TEMPLATE),
+                (Transform::at(10., 10.), SIMPLE_BACKPACK_TEMPLATE),
+                (Transform::at(10., 10.), RUNNING_SHOES_TEMPLATE),
+                // (Transform::at(-50., -50.), BANDIT_TEMPLATE),
+            ])
+            .with_seed(seed),
+        );
+        let house_size = 4096.;
+        world.update_with(|state, cmds| {
+            HouseGenerator::new("derelict_house").try_generate(
+                &Rect::new((0., 0.), (house_size, house_size)),
+                state,
+                cmds,
+            );
+            // HouseGenerator::new("derelict_house").try_generate(
+            //     &Rect::new((-600., -600.), (house_size, house_size)),
+            //     state,
+            //     cmds,
+            // );
+        });
+        world
+    }
+
+    /// Saves the state of the given world into the file at the given path.
+    pub fn save(world: &SystemManager<State>, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        world
+            .get_state()
+            .save_to_file(path, &create_snapshot_registry())
+    }
+
+    /// Loads a world from the snapshot file at the given path, registering the default systems.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<SystemManager<State>> {
+        let state = State::load_from_file(path, &create_snapshot_registry())?;
+        let mut world = create_empty_world();
+        world.set_state(state);
+        Ok(world)
+    }
+
+    /// Takes a snapshot of the given world.
+    pub fn snapshot(world: &SystemManager<State>) -> anyhow::Result<StateSnapshot> {
+        world.get_state().to_snapshot(&create_snapshot_registry())
+    }
+
+    /// Restores a world from the given snapshot, registering the default systems.
+    pub fn restore(snapshot: StateSnapshot) -> anyhow::Result<SystemManager<State>> {
+        let state = State::from_snapshot(snapshot, &create_snapshot_registry())?;
+        let mut world = create_empty_world();
+        world.set_state(state);
+        Ok(world)
+    }
 }
@@ -1,4 +1,5 @@
 use crate::ai::*;
+use crate::camera::CameraFollow;
 use crate::controller::*;
 use crate::effects::*;
 use crate::item::*;
@@ -12,6 +13,10 @@ use crate::vehicle::*;
 pub fn create_empty_world<R: StateReader>() -> SystemManager<R> {
     // Create the world from an empty state.
     let mut system_manager = SystemManager::from(R::default());
+    // Systems only read the state of the previous update, so their order matters only when they modify the same components.
+    system_manager
+        .register_system(WorldTimeSystem)
+        .in_stage(Stage::PreUpdate);
     // Control & movement
     system_manager.register_system(MovementSystem);
     system_manager.register_system(AnchorSystem);
@@ -28,7 +33,11 @@ pub fn create_empty_world<R: StateReader>() -> SystemManager<R> {
     system_manager.register_system(EquipmentInteractionSystem);
     system_manager.register_system(UntargetedInteractionDelegateSystem);
     // Basic physics
-    system_manager.register_system(CollisionDetectionSystem);
+    // Collisions should override the movement & the velocity computed in the same update.
+    system_manager
+        .register_system(CollisionDetectionSystem)
+        .after::<MovementSystem>()
+        .after::<ApproachVelocitySystem>();
     system_manager.register_system(InteractionSystem::<Hitbox>::default());
     // Item stuff
     system_manager.register_system(StorageSystem);
@@ -45,22 +54,50 @@ pub fn create_empty_world<R: StateReader>() -> SystemManager<R> {
     // Projectiles
     system_manager.register_system(InteractionSystem::<ProjectileGenerator>::default());
     system_manager.register_system(ProjectileGenerationSystem);
-    system_manager.register_system(HitSystem);
+    // The fast hitters stop where they hit, overriding their movement in the same update.
+    system_manager
+        .register_system(HitSystem)
+        .after::<MovementSystem>();
     system_manager.register_system(SuicideOnHitSystem);
     system_manager.register_system(TimedEmitSystem::<GenerateProjectileReq>::default());
     system_manager.register_system(ApplyOnHitSystem::<NeedMutator>::default());
+    // Ammo
+    system_manager.register_system(InteractionSystem::<Magazine>::default());
+    system_manager.register_system(ReloadSystem);
+    system_manager.register_system(TimedEmitSystem::<CompleteReloadReq>::default());
     // Vehicle stuff
     system_manager.register_system(VehicleSystem);
     system_manager.register_system(InteractionSystem::<Vehicle>::default());
     // AI stuff
     system_manager.register_system(VisionSystem);
     system_manager.register_system(InteractionSystem::<VisionField>::default());
+    system_manager.register_system(MovementNoiseSystem);
+    system_manager.register_system(HearingSystem);
     system_manager.register_system(ControlSystem::<AiDriver>::default());
-    // Sprite
-    system_manager.register_system(SpriteAnimationSystem);
     // Misc
-    system_manager.register_system(TimedRemoveSystem::<NeedMutator>::default());
-    system_manager.register_system(EffectSystem::<MaxSpeed>::default());
-    system_manager.register_system(EffectSystem::<Acceleration>::default());
+    system_manager
+        .register_system(TimedRemoveSystem::<NeedMutator>::default())
+        .in_stage(Stage::PostUpdate);
+    system_manager
+        .register_system(EffectSystem::<MaxSpeed>::default())
+        .in_stage(Stage::PostUpdate);
+    system_manager
+        .register_system(EffectSystem::<Acceleration>::default())
+        .in_stage(Stage::PostUpdate);
+    // Sprite
+    system_manager
+        .register_system(SpriteAnimationSystem)
+        .in_stage(Stage::RenderPrep);
+    system_manager
+        .register_system(ExplorationSystem)
+        .in_stage(Stage::RenderPrep);
+    system_manager.build_schedule().unwrap();
+    // Only a handful of entities have these components.
+    system_manager.update_with(|_, cmds| {
+        cmds.set_storage::<CameraFollow>(StorageKind::Sparse);
+        cmds.set_storage::<Controller<UserInputDriver>>(StorageKind::Sparse);
+        cmds.set_storage::<TimedEmit<GenerateProjectileReq>>(StorageKind::Sparse);
+        cmds.set_storage::<TimedEmit<CompleteReloadReq>>(StorageKind::Sparse);
+    });
     system_manager
 }
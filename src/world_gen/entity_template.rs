@@ -1,6 +1,8 @@
+use rand::Rng;
+
 use crate::{
-    ai::AiDriver, camera::CameraFollow, character::CharacterBundle, controller::*, effects::*,
-    item::*, needs::*, physics::*, prelude::*, sprite::Sprite, vehicle::VehicleBundle,
+    ai::AiDriver, camera::*, character::*, controller::*, effects::*, item::*, needs::*,
+    physics::*, prelude::*, sprite::Sprite, vehicle::VehicleBundle,
 };
 
 pub struct EntityTemplate {
@@ -36,11 +38,23 @@ pub const PLAYER_TEMPLATE: EntityTemplate = EntityTemplate {
                 Sprite::new("player", 3),
                 CameraFollow::new(1200., 1200.),
                 Controller(UserInputDriver),
+                SimulationZone(1000.),
                 Affected::<MaxSpeed>::default(),
                 Affected::<Acceleration>::default(),
             ),
         );
+        cmds.set_resource(Player(*character.primary_entity()));
+        cmds.set_resource(ActiveCamera(*character.primary_entity()));
         // cmds.mark_for_removal(&character.vision_field);
+        // Carry a spare magazine's worth of rounds.
+        for _ in 0..25 {
+            if let Some(round) = RIFLE_ROUND_TEMPLATE.generate(trans, cmds) {
+                cmds.emit_event(ItemTransferReq::equip_from_ground(
+                    round,
+                    *character.primary_entity(),
+                ));
+            }
+        }
         Some(*character.primary_entity())
     },
 };
@@ -53,6 +67,7 @@ pub const BANDIT_TEMPLATE: EntityTemplate = EntityTemplate {
             (
                 Sprite::new("bandit", 3),
                 Controller(AiDriver::default()),
+                SimulationZone(1000.),
                 Affected::<MaxSpeed>::default(),
                 Affected::<Acceleration>::default(),
             ),
@@ -62,6 +77,15 @@ pub const BANDIT_TEMPLATE: EntityTemplate = EntityTemplate {
             bandit_weapon,
             *character.primary_entity(),
         ));
+        // Carry a spare magazine's worth of rounds.
+        for _ in 0..25 {
+            if let Some(round) = RIFLE_ROUND_TEMPLATE.generate(trans, cmds) {
+                cmds.emit_event(ItemTransferReq::equip_from_ground(
+                    round,
+                    *character.primary_entity(),
+                ));
+            }
+        }
         Some(*character.primary_entity())
     },
 };
@@ -73,6 +97,16 @@ pub const CHEST_TEMPLATE: EntityTemplate = EntityTemplate {
             storage_bundle.primary_entity(),
             (Sprite::new("chest", 2), Name("some random chest")),
         );
+        // Chests are the main source of ammo, stocked with a random number of rounds of each calibre.
+        let chest = *storage_bundle.primary_entity();
+        for round_template in [PISTOL_ROUND_TEMPLATE, RIFLE_ROUND_TEMPLATE] {
+            let num_rounds = cmds.rng().gen_range(10..=50);
+            for _ in 0..num_rounds {
+                if let Some(round) = round_template.generate(trans, cmds) {
+                    cmds.emit_event(ItemTransferReq::pick_up(round, chest));
+                }
+            }
+        }
         Some(*storage_bundle.primary_entity())
     },
 };
@@ -99,6 +133,8 @@ pub const HAND_GUN_TEMPLATE: EntityTemplate = EntityTemplate {
                 ProjectileGenerator {
                     auto_knockback: None,
                     cooldown: None,
+                    loudness: 600.,
+                    calibre: Some(Calibre::Pistol),
                     proj: ProjectileDefn {
                         lifetime: 0.5,
                         speed: 300.,
@@ -133,6 +169,8 @@ pub const MACHINE_GUN_TEMPLATE: EntityTemplate = EntityTemplate {
                 ProjectileGenerator {
                     auto_knockback: Some(100.),
                     cooldown: Some(0.05),
+                    loudness: 1000.,
+                    calibre: Some(Calibre::Rifle),
                     proj: ProjectileDefn {
                         lifetime: 1.5,
                         speed: 2000.,
@@ -140,11 +178,48 @@ pub const MACHINE_GUN_TEMPLATE: EntityTemplate = EntityTemplate {
                         on_hit: NeedMutator::new(NeedType::Health, NeedMutatorEffect::Delta(-5.)),
                     },
                 },
+                Magazine::new(30, 2.),
+                InteractTarget::<Magazine>::default(),
             ),
         );
+        // Comes loaded.
+        for _ in 0..30 {
+            if let Some(round) = RIFLE_ROUND_TEMPLATE.generate(trans, cmds) {
+                cmds.emit_event(ItemTransferReq::pick_up(round, item));
+            }
+        }
+        Some(item)
+    },
+};
+
+pub const PISTOL_ROUND_TEMPLATE: EntityTemplate = EntityTemplate {
+    generator: |trans, cmds| {
+        let item = create_item(
+            Item::stackable(50),
+            trans,
+            Name("PistolRound"),
+            SlotSelector::new([[EquipmentSlot::WeaponAmmo]]),
+            cmds,
+        );
+        cmds.set_components(&item, (Ammo(Calibre::Pistol),));
         Some(item)
     },
 };
+
+pub const RIFLE_ROUND_TEMPLATE: EntityTemplate = EntityTemplate {
+    generator: |trans, cmds| {
+        let item = create_item(
+            Item::stackable(25),
+            trans,
+            Name("RifleRound"),
+            SlotSelector::new([[EquipmentSlot::WeaponAmmo]]),
+            cmds,
+        );
+        cmds.set_components(&item, (Ammo(Calibre::Rifle),));
+        Some(item)
+    },
+};
+
 pub const RUNNING_SHOES_TEMPLATE: EntityTemplate = EntityTemplate {
     generator: |trans, cmds| {
         let item = create_item(
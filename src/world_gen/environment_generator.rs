@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
 use itertools::Itertools;
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use rand::{seq::SliceRandom, Rng};
 
 use crate::{
     building::{BuildingBundle, WallDirection},
@@ -36,7 +36,6 @@ impl EnvGenerator for HouseGenerator {
         state: &impl StateReader,
         cmds: &mut StateCommands,
     ) -> Option<EntityRef> {
-        let mut rng = thread_rng();
         let possible_sizes = [192., 256., 320.];
         let mut curr_pos = available_space.min;
         let mut available_x = available_space.w();
@@ -48,21 +47,21 @@ impl EnvGenerator for HouseGenerator {
                     .iter()
                     .filter(|size| *size <= &available_x)
                     .collect_vec();
-                available_sizes.choose(&mut rng).cloned()
+                available_sizes.choose(cmds.rng()).cloned()
             };
             let chosen_size_y = {
                 let available_sizes = possible_sizes
                     .iter()
                     .filter(|size| *size <= &available_y)
                     .collect_vec();
-                available_sizes.choose(&mut rng).cloned()
+                available_sizes.choose(cmds.rng()).cloned()
             };
             let chosen_direction = match (chosen_size_x, chosen_size_y) {
                 (None, None) => None,
                 (None, Some(dy)) => Some((dy, WallDirection::Bottom)),
                 (Some(dx), None) => Some((dx, WallDirection::Right)),
                 (Some(dx), Some(dy)) => {
-                    if thread_rng().gen() {
+                    if cmds.rng().gen() {
                         Some((dy, WallDirection::Bottom))
                     } else {
                         Some((dx, WallDirection::Right))
@@ -184,3 +183,31 @@ impl EnvGenerator for RoomGenerator {
         Some(*building.primary_entity())
     }
 }
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::world_gen::*;
+
+    #[test]
+    fn test_seeded_house_generation() {
+        let generate_rooms = |seed: u64| {
+            let mut world = WorldGenerator::generate(WorldTemplate::new([]).with_seed(seed));
+            world.update_with(|state, cmds| {
+                HouseGenerator::new("derelict_house").try_generate(
+                    &Rect::new((0., 0.), (2048., 2048.)),
+                    state,
+                    cmds,
+                );
+            });
+            world
+                .get_state()
+                .select::<(Transform,)>()
+                .map(|(_, (trans,))| (trans.x, trans.y))
+                .collect::<Vec<_>>()
+        };
+        assert!(!generate_rooms(7).is_empty());
+        assert_eq!(generate_rooms(7), generate_rooms(7));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::ai::*;
+use crate::building::*;
+use crate::camera::*;
+use crate::character::*;
+use crate::controller::*;
+use crate::effects::*;
+use crate::item::*;
+use crate::needs::*;
+use crate::physics::*;
+use crate::prelude::*;
+use crate::sprite::*;
+use crate::vehicle::*;
+
+/// Registers all the component, resource & event types of the game, so that the worlds can be saved & loaded.
+pub fn create_snapshot_registry() -> SnapshotRegistry {
+    let mut registry = SnapshotRegistry::default();
+    // Basic components
+    registry.register::<Transform>();
+    registry.register::<Offset>();
+    registry.register::<Velocity>();
+    registry.register::<TargetVelocity>();
+    registry.register::<TargetRotation>();
+    registry.register::<MaxSpeed>();
+    registry.register::<Acceleration>();
+    registry.register::<AnchorTransform>();
+    registry.register::<Name>();
+    registry.register::<Lifetime>();
+    registry.register::<CameraFollow>();
+    registry.register::<Sprite>();
+    // Control
+    registry.register::<Controller<UserInputDriver>>();
+    registry.register::<Controller<AiDriver>>();
+    registry.register::<ProximityInteractable>();
+    registry.register::<UntargetedInteractionDelegate>();
+    // Interactions
+    registry.register::<InteractTarget<Hitbox>>();
+    registry.register::<InteractTarget<VisionField>>();
+    registry.register::<InteractTarget<Item>>();
+    registry.register::<InteractTarget<Storage>>();
+    registry.register::<InteractTarget<Equipment>>();
+    registry.register::<InteractTarget<ProjectileGenerator>>();
+    registry.register::<InteractTarget<Vehicle>>();
+    registry.register::<InteractTarget<Magazine>>();
+    registry.register::<InteractActor<Hitbox>>();
+    registry.register::<InteractActor<VisionField>>();
+    registry.register::<InteractActor<Item>>();
+    registry.register::<InteractActor<Storage>>();
+    registry.register::<InteractActor<Equipment>>();
+    registry.register::<InteractActor<ProjectileGenerator>>();
+    registry.register::<InteractActor<Vehicle>>();
+    registry.register::<InteractActor<Magazine>>();
+    // Physics
+    registry.register::<Hitbox>();
+    registry.register::<RigidBody>();
+    registry.register::<SimulationZone>();
+    registry.register::<VisionField>();
+    registry.register::<VisionAwareness>();
+    registry.register::<Hearing>();
+    registry.register::<MovementNoise>();
+    registry.register::<ProjectileGenerator>();
+    registry.register::<Hitter>();
+    registry.register::<FastMoving>();
+    registry.register::<SuicideOnHit>();
+    registry.register::<ApplyOnHit<NeedMutator>>();
+    registry.register::<TimedEmit<GenerateProjectileReq>>();
+    // Items
+    registry.register::<Item>();
+    registry.register::<Storage>();
+    registry.register::<Equipment>();
+    registry.register::<Equippable>();
+    registry.register::<Ammo>();
+    registry.register::<Magazine>();
+    registry.register::<TimedEmit<CompleteReloadReq>>();
+    // Needs
+    registry.register::<Needs>();
+    registry.register::<HandledNeeds>();
+    registry.register::<NeedMutator>();
+    registry.register::<TimedRemove<NeedMutator>>();
+    // Effects
+    registry.register::<Affected<MaxSpeed>>();
+    registry.register::<Affected<Acceleration>>();
+    registry.register::<Effector<MaxSpeed>>();
+    registry.register::<Effector<Acceleration>>();
+    // Tags
+    registry.register::<Character>();
+    registry.register::<Vehicle>();
+    registry.register::<Building>();
+    // Resources
+    registry.register_resource::<WorldTime>();
+    registry.register_resource::<Player>();
+    registry.register_resource::<ActiveCamera>();
+    registry.register_resource::<Explored>();
+    // Events
+    registry.register_event::<ItemTransferReq>();
+    registry.register_event::<GenerateProjectileReq>();
+    registry.register_event::<HitEvt>();
+    registry.register_event::<CompleteReloadReq>();
+    registry
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::world_gen::*;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut world = WorldGenerator::generate(WorldTemplate::new([
+            (Transform::at(-40., -40.), PLAYER_TEMPLATE),
+            (Transform::at(50., 50.), CHEST_TEMPLATE),
+            (Transform::at(500., 500.), BASIC_CAR_TEMPLATE),
+            (Transform::at(10., 10.), MACHINE_GUN_TEMPLATE),
+            (Transform::at(10., 10.), SIMPLE_BACKPACK_TEMPLATE),
+            (Transform::at(-50., -50.), BANDIT_TEMPLATE),
+        ]));
+        world.update_with(|state, cmds| {
+            HouseGenerator::new("derelict_house").try_generate(
+                &Rect::new((0., 0.), (1024., 1024.)),
+                state,
+                cmds,
+            );
+        });
+        for _ in 0..10 {
+            world.update_with_systems(UpdateContext {
+                dt: 1. / 60.,
+                ..Default::default()
+            });
+        }
+        let registry = create_snapshot_registry();
+        let state = world.get_state();
+        let snapshot = state.to_snapshot(&registry).unwrap();
+        let loaded = State::from_snapshot(snapshot, &registry).unwrap();
+        let transforms = |state: &State| {
+            state
+                .select::<(Transform,)>()
+                .map(|(e, (trans,))| (e, trans.x, trans.y, trans.deg))
+                .collect::<Vec<_>>()
+        };
+        assert!(!transforms(state).is_empty());
+        assert_eq!(transforms(state), transforms(&loaded));
+        assert_eq!(
+            state.select::<(Character,)>().count(),
+            loaded.select::<(Character,)>().count()
+        );
+        let player = state.select::<(Controller<UserInputDriver>,)>().next();
+        assert!(player.is_some());
+        assert!(loaded
+            .read_bundle::<CharacterBundle>(&player.unwrap().0)
+            .is_some());
+        assert_eq!(
+            loaded.read_resource::<Player>().map(|player| player.0),
+            Some(player.unwrap().0)
+        );
+        assert_eq!(
+            loaded.read_resource::<WorldTime>().map(|time| time.frame),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_bytes() {
+        use crate::headless::{HeadlessRunner, InputScript};
+        // The same seed & inputs are saved into the same bytes, regardless of the order of the sets & maps in memory.
+        let save = || {
+            let world = WorldGenerator::generate_debug_world(7);
+            let mut runner = HeadlessRunner::new(world, InputScript::Wander);
+            runner.run(60).unwrap();
+            serde_json::to_string(&WorldGenerator::snapshot(runner.world()).unwrap()).unwrap()
+        };
+        assert!(save() == save());
+    }
+
+    #[test]
+    fn test_snapshot_continuation() {
+        // Two players with machine guns, which draw the spread of their shots from the same random number generator.
+        // The gun of the first player is created last, so the cooldowns of the guns are not stored in the order of their ids.
+        let mut world = WorldGenerator::generate(
+            WorldTemplate::new([
+                (Transform::at(0., -100.), PLAYER_TEMPLATE),
+                (Transform::at(0., 100.), MACHINE_GUN_TEMPLATE),
+                (Transform::at(0., -100.), MACHINE_GUN_TEMPLATE),
+                (Transform::at(0., 100.), PLAYER_TEMPLATE),
+            ])
+            .with_seed(5),
+        );
+        let mut shooters = vec![];
+        world.update_with(|state, cmds| {
+            for (player, (_, player_trans)) in
+                state.select::<(Controller<UserInputDriver>, Transform)>()
+            {
+                let (gun, _) = state
+                    .select::<(ProjectileGenerator, Transform)>()
+                    .find(|(_, (_, trans))| trans.y == player_trans.y)
+                    .unwrap();
+                cmds.emit_event(ItemTransferReq::equip_from_ground(gun, player));
+                shooters.push((player, gun));
+            }
+        });
+        let step = |world: &mut SystemManager<State>, frame: usize| {
+            world.update_with(|_, cmds| {
+                if frame == 10 {
+                    shooters.iter().for_each(|(player, gun)| {
+                        cmds.emit_event(InteractReq::<ProjectileGenerator>::new(*player, *gun))
+                    });
+                }
+            });
+            world.update_with_systems(UpdateContext {
+                dt: 1. / 60.,
+                ..Default::default()
+            });
+        };
+        let shots = |world: &SystemManager<State>| {
+            world
+                .get_state()
+                .select::<(Hitter, Transform)>()
+                .map(|(e, (_, trans))| (e, trans.x, trans.y, trans.deg))
+                .collect::<Vec<_>>()
+        };
+        let mut live = WorldGenerator::restore(WorldGenerator::snapshot(&world).unwrap()).unwrap();
+        (0..80).for_each(|frame| step(&mut live, frame));
+        // The same run, saved & loaded in the middle of the fire.
+        (0..45).for_each(|frame| step(&mut world, frame));
+        let mut resumed =
+            WorldGenerator::restore(WorldGenerator::snapshot(&world).unwrap()).unwrap();
+        (45..80).for_each(|frame| step(&mut resumed, frame));
+        assert!(shots(&live).len() >= 2);
+        assert_eq!(shots(&live), shots(&resumed));
+    }
+}